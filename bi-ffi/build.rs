@@ -0,0 +1,26 @@
+use std::env;
+use std::path::PathBuf;
+
+/// When set, the header is also written to `include/bi.h` in the source tree.
+const UPDATE_HEADER_VAR: &str = "BI_FFI_UPDATE_HEADER";
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+        .expect("Failed to read cbindgen.toml");
+
+    let bindings = cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Failed to generate C header");
+    bindings.write_to_file(out_dir.join("bi.h"));
+    if env::var_os(UPDATE_HEADER_VAR).is_some() {
+        bindings.write_to_file(crate_dir.join("include").join("bi.h"));
+    }
+
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed={}", UPDATE_HEADER_VAR);
+}
@@ -0,0 +1,262 @@
+#ifndef BI_H
+#define BI_H
+
+/* Generated by cbindgen from bi-ffi, do not edit manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Set of field markers accepted by a reader or writer, mirrors `bi_parser::Dialect`. Functions
+// take the dialect as an integer so that an out of range value from C can be rejected.
+typedef enum BiDialect {
+  // `:i` and `:b` only.
+  BI_DIALECT_STRICT = 0,
+  // Adds the `:s` signed integer marker.
+  BI_DIALECT_SIGNED = 1,
+  // Adds the `:f` float and `:t` bool markers.
+  BI_DIALECT_EXTENDED = 2,
+} BiDialect;
+
+// Kind of a field, determines which value accessor applies.
+typedef enum BiFieldKind {
+  // `:i` field, read with `bi_field_uint`.
+  BI_FIELD_KIND_INTEGER = 0,
+  // `:s` field, read with `bi_field_sint`.
+  BI_FIELD_KIND_SIGNED_INTEGER = 1,
+  // `:i` field wider than 64 bits, read with `bi_field_big_integer`.
+  BI_FIELD_KIND_BIG_INTEGER = 2,
+  // `:s` field wider than 64 bits, read with `bi_field_big_integer`.
+  BI_FIELD_KIND_BIG_SIGNED_INTEGER = 3,
+  // `:f` field, read with `bi_field_float`.
+  BI_FIELD_KIND_FLOAT = 4,
+  // `:t` field, read with `bi_field_bool`.
+  BI_FIELD_KIND_BOOL = 5,
+  // `:b` field, read with `bi_field_blob`.
+  BI_FIELD_KIND_BLOB = 6,
+  // Returned by `bi_field_kind` for a null field.
+  BI_FIELD_KIND_INVALID = -1,
+} BiFieldKind;
+
+// Status code returned by the C API functions.
+typedef enum BiStatus {
+  // The call succeeded.
+  BI_STATUS_OK = 0,
+  // The reader has no more fields.
+  BI_STATUS_EOF = 1,
+  // A required pointer argument was null.
+  BI_STATUS_NULL_POINTER = -1,
+  // An argument was invalid (e.g. a path that isn't valid UTF-8).
+  BI_STATUS_INVALID_ARGUMENT = -2,
+  // Low level I/O error.
+  BI_STATUS_IO = -3,
+  // The input is not well formed bi data (e.g. truncated).
+  BI_STATUS_PARSE = -4,
+  // Field content failed validation.
+  BI_STATUS_VALIDATION = -5,
+  // Writing to the output failed.
+  BI_STATUS_WRITE = -6,
+  // The field accessor doesn't match the field type.
+  BI_STATUS_WRONG_TYPE = -7,
+} BiStatus;
+
+// Opaque field read from a bi file. Free with `bi_field_free`.
+typedef struct BiFieldHandle BiFieldHandle;
+
+// Opaque reader over a bi file. Close with `bi_reader_close`.
+typedef struct BiReaderHandle BiReaderHandle;
+
+// Opaque writer to a bi file. Close with `bi_writer_close`.
+typedef struct BiWriterHandle BiWriterHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns the message of the last error raised on the calling thread, or null if there was none.
+//
+// The returned string is owned by the library and stays valid until the next failing call on
+// the same thread.
+const char *bi_last_error_message(void);
+
+// Clears the last error of the calling thread.
+void bi_clear_last_error(void);
+
+// Returns the kind of the field, or `BI_FIELD_KIND_INVALID` if `field` is null.
+//
+// # Safety
+// `field` must be null or a handle returned by `bi_reader_next` that has not been freed.
+enum BiFieldKind bi_field_kind(const struct BiFieldHandle *field);
+
+// Returns the marker byte of the field (e.g. `'i'`, `'b'`), or 0 if `field` is null.
+//
+// # Safety
+// `field` must be null or a handle returned by `bi_reader_next` that has not been freed.
+uint8_t bi_field_marker(const struct BiFieldHandle *field);
+
+// Returns a pointer to the field name bytes (not nul terminated) and writes its length to
+// `len`. The pointer is valid until the field is freed. Returns null if `field` or `len` is
+// null.
+//
+// # Safety
+// `field` must be null or a handle returned by `bi_reader_next` that has not been freed and
+// `len` must be null or a valid pointer.
+const uint8_t *bi_field_name(const struct BiFieldHandle *field, size_t *len);
+
+// Reads the value of an `Integer` field.
+//
+// # Safety
+// `field` must be a handle returned by `bi_reader_next` that has not been freed and `out` must
+// be a valid pointer.
+enum BiStatus bi_field_uint(const struct BiFieldHandle *field, uint64_t *out);
+
+// Reads the value of a `SignedInteger` field.
+//
+// # Safety
+// `field` must be a handle returned by `bi_reader_next` that has not been freed and `out` must
+// be a valid pointer.
+enum BiStatus bi_field_sint(const struct BiFieldHandle *field, int64_t *out);
+
+// Reads the decimal text of a `BigInteger` or `BigSignedInteger` field. The pointer (not nul
+// terminated) is valid until the field is freed.
+//
+// # Safety
+// `field` must be a handle returned by `bi_reader_next` that has not been freed and `text` and
+// `len` must be valid pointers.
+enum BiStatus bi_field_big_integer(const struct BiFieldHandle *field,
+                                   const uint8_t **text,
+                                   size_t *len);
+
+// Reads the value of a `Float` field.
+//
+// # Safety
+// `field` must be a handle returned by `bi_reader_next` that has not been freed and `out` must
+// be a valid pointer.
+enum BiStatus bi_field_float(const struct BiFieldHandle *field, double *out);
+
+// Reads the value of a `Bool` field.
+//
+// # Safety
+// `field` must be a handle returned by `bi_reader_next` that has not been freed and `out` must
+// be a valid pointer.
+enum BiStatus bi_field_bool(const struct BiFieldHandle *field, bool *out);
+
+// Reads the content of a `Blob` field. The pointer is valid until the field is freed.
+//
+// # Safety
+// `field` must be a handle returned by `bi_reader_next` that has not been freed and `data` and
+// `len` must be valid pointers.
+enum BiStatus bi_field_blob(const struct BiFieldHandle *field, const uint8_t **data, size_t *len);
+
+// Frees a field. Passing null is a no-op.
+//
+// # Safety
+// `field` must be null or a handle returned by `bi_reader_next` that has not been freed.
+void bi_field_free(struct BiFieldHandle *field);
+
+// Opens the bi file at `path` (nul terminated, UTF-8) for reading fields of the given
+// `BiDialect`.
+//
+// # Safety
+// `path` must be a valid nul terminated string and `out` a valid pointer.
+enum BiStatus bi_reader_open(const char *path, uint32_t dialect, struct BiReaderHandle **out);
+
+// Enables or disables validation of read fields (enabled by default).
+//
+// # Safety
+// `reader` must be a handle returned by `bi_reader_open` that has not been closed.
+enum BiStatus bi_reader_set_validate(struct BiReaderHandle *reader, bool validate);
+
+// Reads the next field. Returns `BI_STATUS_EOF` (leaving `out` untouched) once every field has
+// been read. The field must be freed with `bi_field_free`.
+//
+// # Safety
+// `reader` must be a handle returned by `bi_reader_open` that has not been closed and `out` a
+// valid pointer.
+enum BiStatus bi_reader_next(struct BiReaderHandle *reader, struct BiFieldHandle **out);
+
+// Closes the reader. Passing null is a no-op.
+//
+// # Safety
+// `reader` must be null or a handle returned by `bi_reader_open` that has not been closed.
+void bi_reader_close(struct BiReaderHandle *reader);
+
+// Creates (or truncates) the bi file at `path` (nul terminated, UTF-8) for writing fields of
+// the given `BiDialect`.
+//
+// # Safety
+// `path` must be a valid nul terminated string and `out` a valid pointer.
+enum BiStatus bi_writer_create(const char *path, uint32_t dialect, struct BiWriterHandle **out);
+
+// Enables or disables validation of written fields (enabled by default).
+//
+// # Safety
+// `writer` must be a handle returned by `bi_writer_create` that has not been closed.
+enum BiStatus bi_writer_set_validate(struct BiWriterHandle *writer, bool validate);
+
+// Writes an `:i` field.
+//
+// # Safety
+// `writer` must be a handle returned by `bi_writer_create` that has not been closed and `name`
+// must point to `name_len` readable bytes.
+enum BiStatus bi_writer_write_uint(struct BiWriterHandle *writer,
+                                   const uint8_t *name,
+                                   size_t name_len,
+                                   uint64_t value);
+
+// Writes an `:s` field.
+//
+// # Safety
+// `writer` must be a handle returned by `bi_writer_create` that has not been closed and `name`
+// must point to `name_len` readable bytes.
+enum BiStatus bi_writer_write_sint(struct BiWriterHandle *writer,
+                                   const uint8_t *name,
+                                   size_t name_len,
+                                   int64_t value);
+
+// Writes an `:f` field (requires the extended dialect).
+//
+// # Safety
+// `writer` must be a handle returned by `bi_writer_create` that has not been closed and `name`
+// must point to `name_len` readable bytes.
+enum BiStatus bi_writer_write_float(struct BiWriterHandle *writer,
+                                    const uint8_t *name,
+                                    size_t name_len,
+                                    double value);
+
+// Writes a `:t` field (requires the extended dialect).
+//
+// # Safety
+// `writer` must be a handle returned by `bi_writer_create` that has not been closed and `name`
+// must point to `name_len` readable bytes.
+enum BiStatus bi_writer_write_bool(struct BiWriterHandle *writer,
+                                   const uint8_t *name,
+                                   size_t name_len,
+                                   bool value);
+
+// Writes a `:b` field.
+//
+// # Safety
+// `writer` must be a handle returned by `bi_writer_create` that has not been closed, `name`
+// must point to `name_len` readable bytes, and `data` to `data_len` readable bytes (`data` may
+// be null when `data_len` is 0).
+enum BiStatus bi_writer_write_blob(struct BiWriterHandle *writer,
+                                   const uint8_t *name,
+                                   size_t name_len,
+                                   const uint8_t *data,
+                                   size_t data_len);
+
+// Flushes buffered fields and closes the writer and its file. The handle is freed even when
+// flushing fails. Passing null is a no-op.
+//
+// # Safety
+// `writer` must be null or a handle returned by `bi_writer_create` that has not been closed.
+enum BiStatus bi_writer_close(struct BiWriterHandle *writer);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BI_H */
@@ -0,0 +1,85 @@
+//! # Error Module
+//!
+//! Provides the numeric status codes returned by every C API function and the thread local last
+//! error message.
+
+use bi_parser::parser::error::BiParserError;
+use bi_parser::prelude::BiError;
+use bi_parser::writer::error::BiWriterError;
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+
+/// Status code returned by the C API functions.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BiStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// The reader has no more fields.
+    Eof = 1,
+    /// A required pointer argument was null.
+    NullPointer = -1,
+    /// An argument was invalid (e.g. a path that isn't valid UTF-8).
+    InvalidArgument = -2,
+    /// Low level I/O error.
+    Io = -3,
+    /// The input is not well formed bi data (e.g. truncated).
+    Parse = -4,
+    /// Field content failed validation.
+    Validation = -5,
+    /// Writing to the output failed.
+    Write = -6,
+    /// The field accessor doesn't match the field type.
+    WrongType = -7,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Records `message` as the last error of the calling thread.
+pub(crate) fn set_last_error(message: impl Into<Vec<u8>>) {
+    let mut bytes = message.into();
+    bytes.retain(|b| *b != 0);
+    let message = CString::new(bytes).expect("interior nul bytes were removed");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Records that the `argument` pointer was null as the last error.
+pub(crate) fn null_pointer(argument: &str) -> BiStatus {
+    set_last_error(format!("`{}` is null", argument));
+    BiStatus::NullPointer
+}
+
+/// Records the error as the last error and maps it to a status code.
+pub(crate) fn status_from_error(error: BiError) -> BiStatus {
+    let status = match &error {
+        BiError::IoError(_) => BiStatus::Io,
+        BiError::ParseError(BiParserError::ValidationError(_)) => BiStatus::Validation,
+        BiError::ParseError(_) => BiStatus::Parse,
+        BiError::WriteError(BiWriterError::ValidationError(_)) => BiStatus::Validation,
+        BiError::WriteError(_) => BiStatus::Write,
+        BiError::ValidationError(_) => BiStatus::Validation,
+    };
+    set_last_error(error.to_string());
+    status
+}
+
+/// Returns the message of the last error raised on the calling thread, or null if there was none.
+///
+/// The returned string is owned by the library and stays valid until the next failing call on
+/// the same thread.
+#[no_mangle]
+pub extern "C" fn bi_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Clears the last error of the calling thread.
+#[no_mangle]
+pub extern "C" fn bi_clear_last_error() {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+}
@@ -0,0 +1,254 @@
+//! # Field Module
+//!
+//! Provides the opaque `BiFieldHandle` returned by the reader and its accessors.
+
+use crate::error::{null_pointer, set_last_error, BiStatus};
+use bi_parser::prelude::BiField;
+
+/// Opaque field read from a bi file. Free with `bi_field_free`.
+pub struct BiFieldHandle {
+    pub(crate) inner: BiField,
+}
+
+/// Kind of a field, determines which value accessor applies.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BiFieldKind {
+    /// `:i` field, read with `bi_field_uint`.
+    Integer = 0,
+    /// `:s` field, read with `bi_field_sint`.
+    SignedInteger = 1,
+    /// `:i` field wider than 64 bits, read with `bi_field_big_integer`.
+    BigInteger = 2,
+    /// `:s` field wider than 64 bits, read with `bi_field_big_integer`.
+    BigSignedInteger = 3,
+    /// `:f` field, read with `bi_field_float`.
+    Float = 4,
+    /// `:t` field, read with `bi_field_bool`.
+    Bool = 5,
+    /// `:b` field, read with `bi_field_blob`.
+    Blob = 6,
+    /// Returned by `bi_field_kind` for a null field.
+    Invalid = -1,
+}
+
+fn wrong_type(expected: &str) -> BiStatus {
+    set_last_error(format!("field is not {}", expected));
+    BiStatus::WrongType
+}
+
+/// Returns the kind of the field, or `BI_FIELD_KIND_INVALID` if `field` is null.
+///
+/// # Safety
+/// `field` must be null or a handle returned by `bi_reader_next` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bi_field_kind(field: *const BiFieldHandle) -> BiFieldKind {
+    if field.is_null() {
+        null_pointer("field");
+        return BiFieldKind::Invalid;
+    }
+    match &(*field).inner {
+        BiField::Integer { .. } => BiFieldKind::Integer,
+        BiField::SignedInteger { .. } => BiFieldKind::SignedInteger,
+        BiField::BigInteger { .. } => BiFieldKind::BigInteger,
+        BiField::BigSignedInteger { .. } => BiFieldKind::BigSignedInteger,
+        BiField::Float { .. } => BiFieldKind::Float,
+        BiField::Bool { .. } => BiFieldKind::Bool,
+        BiField::Blob { .. } => BiFieldKind::Blob,
+    }
+}
+
+/// Returns the marker byte of the field (e.g. `'i'`, `'b'`), or 0 if `field` is null.
+///
+/// # Safety
+/// `field` must be null or a handle returned by `bi_reader_next` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bi_field_marker(field: *const BiFieldHandle) -> u8 {
+    if field.is_null() {
+        null_pointer("field");
+        return 0;
+    }
+    (*field).inner.marker().to_byte()
+}
+
+/// Returns a pointer to the field name bytes (not nul terminated) and writes its length to
+/// `len`. The pointer is valid until the field is freed. Returns null if `field` or `len` is
+/// null.
+///
+/// # Safety
+/// `field` must be null or a handle returned by `bi_reader_next` that has not been freed and
+/// `len` must be null or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bi_field_name(field: *const BiFieldHandle, len: *mut usize) -> *const u8 {
+    if field.is_null() {
+        null_pointer("field");
+        return std::ptr::null();
+    }
+    if len.is_null() {
+        null_pointer("len");
+        return std::ptr::null();
+    }
+    let name = (*field).inner.name();
+    *len = name.len();
+    name.as_ptr()
+}
+
+/// Reads the value of an `Integer` field.
+///
+/// # Safety
+/// `field` must be a handle returned by `bi_reader_next` that has not been freed and `out` must
+/// be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bi_field_uint(field: *const BiFieldHandle, out: *mut u64) -> BiStatus {
+    if field.is_null() {
+        return null_pointer("field");
+    }
+    if out.is_null() {
+        return null_pointer("out");
+    }
+    match &(*field).inner {
+        BiField::Integer { value, .. } => {
+            *out = *value;
+            BiStatus::Ok
+        }
+        _ => wrong_type("an integer"),
+    }
+}
+
+/// Reads the value of a `SignedInteger` field.
+///
+/// # Safety
+/// `field` must be a handle returned by `bi_reader_next` that has not been freed and `out` must
+/// be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bi_field_sint(field: *const BiFieldHandle, out: *mut i64) -> BiStatus {
+    if field.is_null() {
+        return null_pointer("field");
+    }
+    if out.is_null() {
+        return null_pointer("out");
+    }
+    match &(*field).inner {
+        BiField::SignedInteger { value, .. } => {
+            *out = *value;
+            BiStatus::Ok
+        }
+        _ => wrong_type("a signed integer"),
+    }
+}
+
+/// Reads the decimal text of a `BigInteger` or `BigSignedInteger` field. The pointer (not nul
+/// terminated) is valid until the field is freed.
+///
+/// # Safety
+/// `field` must be a handle returned by `bi_reader_next` that has not been freed and `text` and
+/// `len` must be valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn bi_field_big_integer(
+    field: *const BiFieldHandle,
+    text: *mut *const u8,
+    len: *mut usize,
+) -> BiStatus {
+    if field.is_null() {
+        return null_pointer("field");
+    }
+    if text.is_null() {
+        return null_pointer("text");
+    }
+    if len.is_null() {
+        return null_pointer("len");
+    }
+    match &(*field).inner {
+        BiField::BigInteger { value, .. } | BiField::BigSignedInteger { value, .. } => {
+            *text = value.as_bytes().as_ptr();
+            *len = value.as_bytes().len();
+            BiStatus::Ok
+        }
+        _ => wrong_type("a big integer"),
+    }
+}
+
+/// Reads the value of a `Float` field.
+///
+/// # Safety
+/// `field` must be a handle returned by `bi_reader_next` that has not been freed and `out` must
+/// be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bi_field_float(field: *const BiFieldHandle, out: *mut f64) -> BiStatus {
+    if field.is_null() {
+        return null_pointer("field");
+    }
+    if out.is_null() {
+        return null_pointer("out");
+    }
+    match &(*field).inner {
+        BiField::Float { value, .. } => {
+            *out = *value;
+            BiStatus::Ok
+        }
+        _ => wrong_type("a float"),
+    }
+}
+
+/// Reads the value of a `Bool` field.
+///
+/// # Safety
+/// `field` must be a handle returned by `bi_reader_next` that has not been freed and `out` must
+/// be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bi_field_bool(field: *const BiFieldHandle, out: *mut bool) -> BiStatus {
+    if field.is_null() {
+        return null_pointer("field");
+    }
+    if out.is_null() {
+        return null_pointer("out");
+    }
+    match &(*field).inner {
+        BiField::Bool { value, .. } => {
+            *out = *value;
+            BiStatus::Ok
+        }
+        _ => wrong_type("a boolean"),
+    }
+}
+
+/// Reads the content of a `Blob` field. The pointer is valid until the field is freed.
+///
+/// # Safety
+/// `field` must be a handle returned by `bi_reader_next` that has not been freed and `data` and
+/// `len` must be valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn bi_field_blob(
+    field: *const BiFieldHandle,
+    data: *mut *const u8,
+    len: *mut usize,
+) -> BiStatus {
+    if field.is_null() {
+        return null_pointer("field");
+    }
+    if data.is_null() {
+        return null_pointer("data");
+    }
+    if len.is_null() {
+        return null_pointer("len");
+    }
+    match &(*field).inner {
+        BiField::Blob { data: blob, .. } => {
+            *data = blob.as_ptr();
+            *len = blob.len();
+            BiStatus::Ok
+        }
+        _ => wrong_type("a blob"),
+    }
+}
+
+/// Frees a field. Passing null is a no-op.
+///
+/// # Safety
+/// `field` must be null or a handle returned by `bi_reader_next` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bi_field_free(field: *mut BiFieldHandle) {
+    if !field.is_null() {
+        drop(Box::from_raw(field));
+    }
+}
@@ -0,0 +1,241 @@
+//! # Bi-FFI Crate
+//!
+//! A C ABI over the `bi-parser` `BiReader` and `BiWriter`, built as `libbi` (`cdylib` and
+//! `staticlib`). The C header is generated into `OUT_DIR` on build, and copied to `include/bi.h`
+//! when `BI_FFI_UPDATE_HEADER` is set.
+//!
+//! ## Conventions
+//! - Every fallible function returns a `BiStatus`, `BI_STATUS_OK` (0) on success and a negative
+//!   code on failure. The error message is available from `bi_last_error_message`.
+//! - Handles are opaque and owned by the caller until passed to their `_close`/`_free` function.
+//! - Dialects are passed as a `uint32_t` holding one of the `BiDialect` values, any other value
+//!   is rejected with `BI_STATUS_INVALID_ARGUMENT`.
+//! - Names and blob content are passed as pointer and length pairs and are not nul terminated.
+//!
+//! ## Example
+//! ```c
+//! BiReaderHandle *reader;
+//! BiFieldHandle *field;
+//! if (bi_reader_open("test.bi", BI_DIALECT_SIGNED, &reader) != BI_STATUS_OK) {
+//!     fprintf(stderr, "%s\n", bi_last_error_message());
+//!     return 1;
+//! }
+//! while (bi_reader_next(reader, &field) == BI_STATUS_OK) {
+//!     size_t len;
+//!     const uint8_t *name = bi_field_name(field, &len);
+//!     printf("%c %.*s\n", bi_field_marker(field), (int)len, name);
+//!     bi_field_free(field);
+//! }
+//! bi_reader_close(reader);
+//! ```
+
+pub mod error;
+pub mod field;
+pub mod reader;
+pub mod writer;
+
+use bi_parser::prelude::Dialect;
+use error::{null_pointer, set_last_error, BiStatus};
+use std::ffi::{c_char, CStr};
+
+/// Set of field markers accepted by a reader or writer, mirrors `bi_parser::Dialect`. Functions
+/// take the dialect as an integer so that an out of range value from C can be rejected.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BiDialect {
+    /// `:i` and `:b` only.
+    Strict = 0,
+    /// Adds the `:s` signed integer marker.
+    Signed = 1,
+    /// Adds the `:f` float and `:t` bool markers.
+    Extended = 2,
+}
+
+impl From<BiDialect> for Dialect {
+    fn from(dialect: BiDialect) -> Self {
+        match dialect {
+            BiDialect::Strict => Dialect::Strict,
+            BiDialect::Signed => Dialect::Signed,
+            BiDialect::Extended => Dialect::Extended,
+        }
+    }
+}
+
+/// Converts a `BiDialect` value received from C to a `Dialect`.
+fn c_dialect(dialect: u32) -> Result<Dialect, BiStatus> {
+    let dialect = match dialect {
+        0 => BiDialect::Strict,
+        1 => BiDialect::Signed,
+        2 => BiDialect::Extended,
+        _ => {
+            set_last_error(format!("{} is not a dialect", dialect));
+            return Err(BiStatus::InvalidArgument);
+        }
+    };
+    Ok(dialect.into())
+}
+
+/// Converts a nul terminated C path to a `&str`.
+unsafe fn c_path<'a>(path: *const c_char) -> Result<&'a str, BiStatus> {
+    if path.is_null() {
+        return Err(null_pointer("path"));
+    }
+    CStr::from_ptr(path).to_str().map_err(|e| {
+        set_last_error(format!("path is not valid UTF-8: {}", e));
+        BiStatus::InvalidArgument
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{bi_clear_last_error, bi_last_error_message};
+    use crate::field::*;
+    use crate::reader::*;
+    use crate::writer::*;
+    use std::ffi::CString;
+    use std::ptr;
+
+    fn temp_path(name: &str) -> CString {
+        let path = std::env::temp_dir().join(format!("bi-ffi-{}-{}", std::process::id(), name));
+        CString::new(path.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = temp_path("round-trip.bi");
+        unsafe {
+            let mut writer = ptr::null_mut();
+            assert_eq!(
+                bi_writer_create(path.as_ptr(), BiDialect::Extended as u32, &mut writer),
+                BiStatus::Ok
+            );
+            assert_eq!(
+                bi_writer_write_uint(writer, b"count".as_ptr(), 5, 42),
+                BiStatus::Ok
+            );
+            assert_eq!(
+                bi_writer_write_float(writer, b"ratio".as_ptr(), 5, 0.5),
+                BiStatus::Ok
+            );
+            assert_eq!(
+                bi_writer_write_blob(writer, b"data".as_ptr(), 4, b"hello".as_ptr(), 5),
+                BiStatus::Ok
+            );
+            assert_eq!(bi_writer_close(writer), BiStatus::Ok);
+
+            let mut reader = ptr::null_mut();
+            assert_eq!(
+                bi_reader_open(path.as_ptr(), BiDialect::Extended as u32, &mut reader),
+                BiStatus::Ok
+            );
+
+            let mut field = ptr::null_mut();
+            assert_eq!(bi_reader_next(reader, &mut field), BiStatus::Ok);
+            assert_eq!(bi_field_kind(field), BiFieldKind::Integer);
+            assert_eq!(bi_field_marker(field), b'i');
+            let mut len = 0;
+            let name = bi_field_name(field, &mut len);
+            assert_eq!(std::slice::from_raw_parts(name, len), b"count");
+            let mut value = 0u64;
+            assert_eq!(bi_field_uint(field, &mut value), BiStatus::Ok);
+            assert_eq!(value, 42);
+            let mut float = 0.0;
+            assert_eq!(bi_field_float(field, &mut float), BiStatus::WrongType);
+            bi_field_free(field);
+
+            assert_eq!(bi_reader_next(reader, &mut field), BiStatus::Ok);
+            assert_eq!(bi_field_float(field, &mut float), BiStatus::Ok);
+            assert_eq!(float, 0.5);
+            bi_field_free(field);
+
+            assert_eq!(bi_reader_next(reader, &mut field), BiStatus::Ok);
+            let mut data = ptr::null();
+            assert_eq!(bi_field_blob(field, &mut data, &mut len), BiStatus::Ok);
+            assert_eq!(std::slice::from_raw_parts(data, len), b"hello");
+            bi_field_free(field);
+
+            assert_eq!(bi_reader_next(reader, &mut field), BiStatus::Eof);
+            bi_reader_close(reader);
+        }
+        std::fs::remove_file(path.to_str().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let path = temp_path("errors.bi");
+        unsafe {
+            let mut reader = ptr::null_mut();
+            assert_eq!(
+                bi_reader_open(path.as_ptr(), BiDialect::Signed as u32, &mut reader),
+                BiStatus::Io
+            );
+            assert!(!bi_last_error_message().is_null());
+            assert_eq!(
+                bi_reader_open(ptr::null(), BiDialect::Signed as u32, &mut reader),
+                BiStatus::NullPointer
+            );
+            let message = CStr::from_ptr(bi_last_error_message()).to_str().unwrap();
+            assert_eq!(message, "`path` is null");
+            assert_eq!(
+                bi_reader_open(path.as_ptr(), 3, &mut reader),
+                BiStatus::InvalidArgument
+            );
+            let message = CStr::from_ptr(bi_last_error_message()).to_str().unwrap();
+            assert_eq!(message, "3 is not a dialect");
+
+            let mut writer = ptr::null_mut();
+            assert_eq!(
+                bi_writer_create(path.as_ptr(), u32::MAX, &mut writer),
+                BiStatus::InvalidArgument
+            );
+            assert_eq!(
+                bi_writer_create(path.as_ptr(), BiDialect::Signed as u32, &mut writer),
+                BiStatus::Ok
+            );
+            // Floats aren't part of the signed dialect.
+            assert_eq!(
+                bi_writer_write_float(writer, b"ratio".as_ptr(), 5, 0.5),
+                BiStatus::Validation
+            );
+            let message = CStr::from_ptr(bi_last_error_message()).to_str().unwrap();
+            assert!(message.contains("not supported"));
+            assert_eq!(
+                bi_writer_write_uint(writer, b"".as_ptr(), 0, 1),
+                BiStatus::Validation
+            );
+            bi_writer_close(writer);
+        }
+        std::fs::write(path.to_str().unwrap(), b":i count 4").unwrap();
+        unsafe {
+            let mut reader = ptr::null_mut();
+            assert_eq!(
+                bi_reader_open(path.as_ptr(), BiDialect::Signed as u32, &mut reader),
+                BiStatus::Ok
+            );
+            let mut field = ptr::null_mut();
+            assert_eq!(bi_reader_next(reader, &mut field), BiStatus::Parse);
+            bi_reader_close(reader);
+        }
+        std::fs::remove_file(path.to_str().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_null_field() {
+        unsafe {
+            bi_clear_last_error();
+            assert_eq!(bi_field_kind(ptr::null()), BiFieldKind::Invalid);
+            assert!(!bi_last_error_message().is_null());
+            assert_eq!(bi_field_marker(ptr::null()), 0);
+            let mut len = 1;
+            assert!(bi_field_name(ptr::null(), &mut len).is_null());
+            let mut value = 0u64;
+            assert_eq!(
+                bi_field_uint(ptr::null(), &mut value),
+                BiStatus::NullPointer
+            );
+            let message = CStr::from_ptr(bi_last_error_message()).to_str().unwrap();
+            assert_eq!(message, "`field` is null");
+        }
+    }
+}
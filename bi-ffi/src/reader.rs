@@ -0,0 +1,110 @@
+//! # Reader Module
+//!
+//! Provides the opaque `BiReaderHandle` wrapping a `BiReader` over a file.
+
+use crate::error::{null_pointer, status_from_error, BiStatus};
+use crate::field::BiFieldHandle;
+use crate::{c_dialect, c_path};
+use bi_parser::prelude::{BiError, BiReader};
+use std::ffi::c_char;
+use std::fs::File;
+
+/// Opaque reader over a bi file. Close with `bi_reader_close`.
+pub struct BiReaderHandle {
+    inner: BiReader<File>,
+    validate: bool,
+}
+
+/// Opens the bi file at `path` (nul terminated, UTF-8) for reading fields of the given
+/// `BiDialect`.
+///
+/// # Safety
+/// `path` must be a valid nul terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bi_reader_open(
+    path: *const c_char,
+    dialect: u32,
+    out: *mut *mut BiReaderHandle,
+) -> BiStatus {
+    if out.is_null() {
+        return null_pointer("out");
+    }
+    let path = match c_path(path) {
+        Ok(path) => path,
+        Err(status) => return status,
+    };
+    let dialect = match c_dialect(dialect) {
+        Ok(dialect) => dialect,
+        Err(status) => return status,
+    };
+    match File::open(path) {
+        Ok(file) => {
+            let handle = BiReaderHandle {
+                inner: BiReader::new(file).with_dialect(dialect),
+                validate: true,
+            };
+            *out = Box::into_raw(Box::new(handle));
+            BiStatus::Ok
+        }
+        Err(e) => status_from_error(BiError::IoError(e)),
+    }
+}
+
+/// Enables or disables validation of read fields (enabled by default).
+///
+/// # Safety
+/// `reader` must be a handle returned by `bi_reader_open` that has not been closed.
+#[no_mangle]
+pub unsafe extern "C" fn bi_reader_set_validate(
+    reader: *mut BiReaderHandle,
+    validate: bool,
+) -> BiStatus {
+    if reader.is_null() {
+        return null_pointer("reader");
+    }
+    (*reader).validate = validate;
+    BiStatus::Ok
+}
+
+/// Reads the next field. Returns `BI_STATUS_EOF` (leaving `out` untouched) once every field has
+/// been read. The field must be freed with `bi_field_free`.
+///
+/// # Safety
+/// `reader` must be a handle returned by `bi_reader_open` that has not been closed and `out` a
+/// valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bi_reader_next(
+    reader: *mut BiReaderHandle,
+    out: *mut *mut BiFieldHandle,
+) -> BiStatus {
+    if reader.is_null() {
+        return null_pointer("reader");
+    }
+    if out.is_null() {
+        return null_pointer("out");
+    }
+    let reader = &mut *reader;
+    match reader.inner.is_eof() {
+        Ok(true) => return BiStatus::Eof,
+        Ok(false) => {}
+        Err(e) => return status_from_error(e),
+    }
+    match reader.inner.read_field(reader.validate) {
+        Ok(field) => {
+            *out = Box::into_raw(Box::new(BiFieldHandle { inner: field }));
+            BiStatus::Ok
+        }
+        Err(e) => status_from_error(e),
+    }
+}
+
+/// Closes the reader. Passing null is a no-op.
+///
+/// # Safety
+/// `reader` must be null or a handle returned by `bi_reader_open` that has not been closed.
+#[no_mangle]
+pub unsafe extern "C" fn bi_reader_close(reader: *mut BiReaderHandle) {
+    if !reader.is_null() {
+        drop(Box::from_raw(reader));
+    }
+}
@@ -0,0 +1,196 @@
+//! # Writer Module
+//!
+//! Provides the opaque `BiWriterHandle` wrapping a `BiWriter` over a file.
+
+use crate::error::{null_pointer, status_from_error, BiStatus};
+use crate::{c_dialect, c_path};
+use bi_parser::prelude::{BiError, BiField, BiWriter};
+use std::ffi::c_char;
+use std::fs::File;
+use std::slice;
+
+/// Opaque writer to a bi file. Close with `bi_writer_close`.
+pub struct BiWriterHandle {
+    inner: BiWriter<File>,
+    validate: bool,
+}
+
+/// Creates (or truncates) the bi file at `path` (nul terminated, UTF-8) for writing fields of
+/// the given `BiDialect`.
+///
+/// # Safety
+/// `path` must be a valid nul terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bi_writer_create(
+    path: *const c_char,
+    dialect: u32,
+    out: *mut *mut BiWriterHandle,
+) -> BiStatus {
+    if out.is_null() {
+        return null_pointer("out");
+    }
+    let path = match c_path(path) {
+        Ok(path) => path,
+        Err(status) => return status,
+    };
+    let dialect = match c_dialect(dialect) {
+        Ok(dialect) => dialect,
+        Err(status) => return status,
+    };
+    match File::create(path) {
+        Ok(file) => {
+            let handle = BiWriterHandle {
+                inner: BiWriter::new(file).with_dialect(dialect),
+                validate: true,
+            };
+            *out = Box::into_raw(Box::new(handle));
+            BiStatus::Ok
+        }
+        Err(e) => status_from_error(BiError::IoError(e)),
+    }
+}
+
+/// Enables or disables validation of written fields (enabled by default).
+///
+/// # Safety
+/// `writer` must be a handle returned by `bi_writer_create` that has not been closed.
+#[no_mangle]
+pub unsafe extern "C" fn bi_writer_set_validate(
+    writer: *mut BiWriterHandle,
+    validate: bool,
+) -> BiStatus {
+    if writer.is_null() {
+        return null_pointer("writer");
+    }
+    (*writer).validate = validate;
+    BiStatus::Ok
+}
+
+/// Builds the field from the name bytes and writes it.
+unsafe fn write(
+    writer: *mut BiWriterHandle,
+    name: *const u8,
+    name_len: usize,
+    build: impl FnOnce(Vec<u8>) -> BiField,
+) -> BiStatus {
+    if writer.is_null() {
+        return null_pointer("writer");
+    }
+    if name.is_null() {
+        return null_pointer("name");
+    }
+    let writer = &mut *writer;
+    let field = build(slice::from_raw_parts(name, name_len).to_vec());
+    match writer.inner.write_field(&field, writer.validate) {
+        Ok(()) => BiStatus::Ok,
+        Err(e) => status_from_error(e),
+    }
+}
+
+/// Writes an `:i` field.
+///
+/// # Safety
+/// `writer` must be a handle returned by `bi_writer_create` that has not been closed and `name`
+/// must point to `name_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bi_writer_write_uint(
+    writer: *mut BiWriterHandle,
+    name: *const u8,
+    name_len: usize,
+    value: u64,
+) -> BiStatus {
+    write(writer, name, name_len, |name| BiField::Integer {
+        name,
+        value,
+    })
+}
+
+/// Writes an `:s` field.
+///
+/// # Safety
+/// `writer` must be a handle returned by `bi_writer_create` that has not been closed and `name`
+/// must point to `name_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bi_writer_write_sint(
+    writer: *mut BiWriterHandle,
+    name: *const u8,
+    name_len: usize,
+    value: i64,
+) -> BiStatus {
+    write(writer, name, name_len, |name| BiField::SignedInteger {
+        name,
+        value,
+    })
+}
+
+/// Writes an `:f` field (requires the extended dialect).
+///
+/// # Safety
+/// `writer` must be a handle returned by `bi_writer_create` that has not been closed and `name`
+/// must point to `name_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bi_writer_write_float(
+    writer: *mut BiWriterHandle,
+    name: *const u8,
+    name_len: usize,
+    value: f64,
+) -> BiStatus {
+    write(writer, name, name_len, |name| BiField::Float {
+        name,
+        value,
+    })
+}
+
+/// Writes a `:t` field (requires the extended dialect).
+///
+/// # Safety
+/// `writer` must be a handle returned by `bi_writer_create` that has not been closed and `name`
+/// must point to `name_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bi_writer_write_bool(
+    writer: *mut BiWriterHandle,
+    name: *const u8,
+    name_len: usize,
+    value: bool,
+) -> BiStatus {
+    write(writer, name, name_len, |name| BiField::Bool { name, value })
+}
+
+/// Writes a `:b` field.
+///
+/// # Safety
+/// `writer` must be a handle returned by `bi_writer_create` that has not been closed, `name`
+/// must point to `name_len` readable bytes, and `data` to `data_len` readable bytes (`data` may
+/// be null when `data_len` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn bi_writer_write_blob(
+    writer: *mut BiWriterHandle,
+    name: *const u8,
+    name_len: usize,
+    data: *const u8,
+    data_len: usize,
+) -> BiStatus {
+    let data = match (data.is_null(), data_len) {
+        (true, 0) => Vec::new(),
+        (true, _) => return null_pointer("data"),
+        (false, _) => slice::from_raw_parts(data, data_len).to_vec(),
+    };
+    write(writer, name, name_len, |name| BiField::Blob { name, data })
+}
+
+/// Flushes buffered fields and closes the writer and its file. The handle is freed even when
+/// flushing fails. Passing null is a no-op.
+///
+/// # Safety
+/// `writer` must be null or a handle returned by `bi_writer_create` that has not been closed.
+#[no_mangle]
+pub unsafe extern "C" fn bi_writer_close(writer: *mut BiWriterHandle) -> BiStatus {
+    if writer.is_null() {
+        return BiStatus::Ok;
+    }
+    let handle = Box::from_raw(writer);
+    match handle.inner.finish() {
+        Ok(_) => BiStatus::Ok,
+        Err(e) => status_from_error(e),
+    }
+}
@@ -0,0 +1,100 @@
+//! Read and write throughput of `BiReader`/`BiWriter` for many small fields and a few large
+//! blobs.
+
+use bi_parser::prelude::*;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Number of fields in the small field workload.
+const SMALL_FIELDS: usize = 10_000;
+/// Size in bytes of each blob in the large blob workload.
+const BLOB_SIZES: [usize; 2] = [64 * 1024, 4 * 1024 * 1024];
+/// Number of blobs in the large blob workload.
+const BLOB_COUNT: usize = 4;
+
+fn small_fields() -> Vec<BiField> {
+    (0..SMALL_FIELDS)
+        .map(|i| match i % 3 {
+            0 => BiField::Integer {
+                name: b"count".to_vec(),
+                value: i as u64 * 7919,
+            },
+            1 => BiField::SignedInteger {
+                name: b"returncode".to_vec(),
+                value: -(i as i64),
+            },
+            _ => BiField::Blob {
+                name: b"shell".to_vec(),
+                data: format!("echo test {}", i).into_bytes(),
+            },
+        })
+        .collect()
+}
+
+fn large_blobs(size: usize) -> Vec<BiField> {
+    (0..BLOB_COUNT)
+        .map(|i| BiField::Blob {
+            name: b"stdout".to_vec(),
+            data: (0..size).map(|j| (i + j) as u8).collect(),
+        })
+        .collect()
+}
+
+fn encode(fields: &[BiField]) -> Vec<u8> {
+    let mut writer = BiWriter::new(Vec::new());
+    for field in fields {
+        writer.write_field_default(field).unwrap();
+    }
+    writer.into_inner().unwrap()
+}
+
+fn write_fields(fields: &[BiField], validate: bool) -> Vec<u8> {
+    let mut writer = BiWriter::new(Vec::new());
+    for field in fields {
+        writer.write_field(field, validate).unwrap();
+    }
+    writer.into_inner().unwrap()
+}
+
+fn read_fields(bytes: &[u8], count: usize, validate: bool) {
+    let mut reader = BiReader::new(bytes);
+    for _ in 0..count {
+        black_box(reader.read_field(validate).unwrap());
+    }
+}
+
+fn bench_small_fields(c: &mut Criterion) {
+    let fields = small_fields();
+    let bytes = encode(&fields);
+
+    let mut group = c.benchmark_group("small_fields");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    for validate in [true, false] {
+        group.bench_with_input(BenchmarkId::new("write", validate), &validate, |b, &v| {
+            b.iter(|| write_fields(black_box(&fields), v))
+        });
+        group.bench_with_input(BenchmarkId::new("read", validate), &validate, |b, &v| {
+            b.iter(|| read_fields(black_box(&bytes), fields.len(), v))
+        });
+    }
+    group.finish();
+}
+
+fn bench_large_blobs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large_blobs");
+    group.sample_size(20);
+    for size in BLOB_SIZES {
+        let fields = large_blobs(size);
+        let bytes = encode(&fields);
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::new("write", size), &fields, |b, fields| {
+            b.iter(|| write_fields(black_box(fields), true))
+        });
+        group.bench_with_input(BenchmarkId::new("read", size), &bytes, |b, bytes| {
+            b.iter(|| read_fields(black_box(bytes), BLOB_COUNT, true))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_small_fields, bench_large_blobs);
+criterion_main!(benches);
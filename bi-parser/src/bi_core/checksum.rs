@@ -0,0 +1,94 @@
+//! # Checksum Module
+//!
+//! Provides the CRC-32 digest used by the trailing checksum field convention.
+//!
+//! A checksummed stream ends with an `:i` field named [`CHECKSUM_NAME`] whose value is the CRC-32
+//! (IEEE 802.3) of every byte before that field, back to the start of the stream or the previous
+//! checksum field. The field is plain bi, so readers that don't verify it see a normal integer.
+
+/// Reserved name of the trailing checksum field.
+pub const CHECKSUM_NAME: &[u8] = b"bi.crc32";
+
+/// How a reader treats checksum fields.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChecksumPolicy {
+    /// Checksum fields are read as plain integers (default).
+    #[default]
+    Ignore,
+    /// Checksum fields are verified against the content before them.
+    Verify,
+    /// Like `Verify`, and the stream must end with a checksum field.
+    Require,
+}
+
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Running CRC-32 (IEEE 802.3) digest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32 {
+    /// Constructor.
+    pub fn new() -> Self {
+        Self { state: !0 }
+    }
+
+    /// Feeds `bytes` into the digest.
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state = TABLE[((self.state ^ *byte as u32) & 0xFF) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    /// Digest of every byte fed in so far.
+    pub fn value(&self) -> u32 {
+        !self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(Crc32::new().value(), 0);
+
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.value(), 0xCBF4_3926);
+
+        let mut split = Crc32::new();
+        split.update(b"1234");
+        split.update(b"56789");
+        assert_eq!(split, crc);
+    }
+}
@@ -0,0 +1,58 @@
+//! # Compression Module
+//!
+//! Provides the gzip helpers behind compressed blobs (requires the `compression` feature).
+//!
+//! A compressed blob is a `:z` field laid out like a `:b` field, `:z name size\ndata\n`, whose
+//! content is the gzip stream of the original data. The declared size is that of the compressed
+//! content, and checksums cover the content as stored.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Error, ErrorKind, Read, Write};
+
+/// Gzip compresses `data`.
+pub fn compress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Decompresses gzip `data`, failing once the decompressed content grows past `limit` bytes.
+pub fn decompress(data: &[u8], limit: usize) -> std::io::Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(data)
+        .take((limit as u64).saturating_add(1))
+        .read_to_end(&mut decoded)?;
+    if decoded.len() > limit {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("decompressed content exceeds the limit of {} bytes", limit),
+        ));
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let data = b"log line\n".repeat(1000);
+        let compressed = compress(&data).unwrap();
+        assert!(compressed.len() < data.len() / 10);
+        assert_eq!(decompress(&compressed, data.len()).unwrap(), data);
+        assert!(decompress(b"not gzip", data.len()).is_err());
+    }
+
+    #[test]
+    fn test_decompression_limit() {
+        let compressed = compress(&[0u8; 4096]).unwrap();
+        assert!(decompress(&compressed, 4096).is_ok());
+        let error = decompress(&compressed, 4095).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("exceeds the limit of 4095 bytes"));
+    }
+}
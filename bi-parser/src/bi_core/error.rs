@@ -11,6 +11,7 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum BiError {
     /// Low level I/O error.
+    #[cfg(feature = "std")]
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
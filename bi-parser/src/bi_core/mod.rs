@@ -2,6 +2,9 @@
 //!
 //! Core functionality and constants for the bi format.
 
+pub mod checksum;
+#[cfg(feature = "compression")]
+pub mod compression;
 pub mod error;
 pub mod types;
 
@@ -9,6 +12,11 @@ pub const MARKER_SYM: u8 = b':';
 pub const MARKER_INT: u8 = b'i';
 pub const MARKER_SINT: u8 = b's';
 pub const MARKER_BLOB: u8 = b'b';
+pub const MARKER_FLOAT: u8 = b'f';
+pub const MARKER_BOOL: u8 = b't';
+pub const MARKER_GZIP_BLOB: u8 = b'z';
 pub const SPACE: u8 = b' ';
 pub const NEWLINE: u8 = b'\n';
+/// Default cap on the decompressed size of a `:z` blob, see the `compression` module.
+pub const DEFAULT_DECOMPRESSION_LIMIT: usize = 256 << 20;
 pub const BIDOCS: &str = "https://github.com/tsoding/bi-format/blob/main/README.md";
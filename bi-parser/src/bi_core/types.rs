@@ -3,7 +3,14 @@
 //! This module provides the funamental types used throughout the library, including field types
 //! and markers.
 
-use std::fmt;
+use crate::bi_core::{
+    MARKER_BLOB, MARKER_BOOL, MARKER_FLOAT, MARKER_GZIP_BLOB, MARKER_INT, MARKER_SINT,
+};
+use crate::validator::error::BiValidationError;
+use crate::validator::utils::validate_big_signed_integer;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
 
 /// Represents a field in the bi format. A field can be either an Integer or a Blob, each with an
 /// associated name.
@@ -13,10 +20,47 @@ pub enum BiField {
     Integer { name: Vec<u8>, value: u64 },
     /// A signed integer field with the format `:s name value\n`
     SignedInteger { name: Vec<u8>, value: i64 },
+    /// An integer field with the format `:i name value\n` whose value does not fit in a `u64`.
+    BigInteger { name: Vec<u8>, value: BigInt },
+    /// A signed integer field with the format `:s name value\n` whose value does not fit in an
+    /// `i64`.
+    BigSignedInteger { name: Vec<u8>, value: BigInt },
+    /// A floating point field with the format `:f name value\n` (extended dialect only).
+    Float { name: Vec<u8>, value: f64 },
+    /// A boolean field with the format `:t name true|false\n` (extended dialect only).
+    Bool { name: Vec<u8>, value: bool },
     /// A blob field with the format `:b name size\ndata\n`
     Blob { name: Vec<u8>, data: Vec<u8> },
 }
 
+impl BiField {
+    /// Returns the marker type of the field.
+    pub fn marker(&self) -> FieldMarker {
+        match self {
+            BiField::Integer { .. } | BiField::BigInteger { .. } => FieldMarker::Integer,
+            BiField::SignedInteger { .. } | BiField::BigSignedInteger { .. } => {
+                FieldMarker::SignedInteger
+            }
+            BiField::Float { .. } => FieldMarker::Float,
+            BiField::Bool { .. } => FieldMarker::Bool,
+            BiField::Blob { .. } => FieldMarker::Blob,
+        }
+    }
+
+    /// Returns the raw name bytes of the field.
+    pub fn name(&self) -> &[u8] {
+        match self {
+            BiField::Integer { name, .. }
+            | BiField::SignedInteger { name, .. }
+            | BiField::BigInteger { name, .. }
+            | BiField::BigSignedInteger { name, .. }
+            | BiField::Float { name, .. }
+            | BiField::Bool { name, .. }
+            | BiField::Blob { name, .. } => name,
+        }
+    }
+}
+
 impl fmt::Display for BiField {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -26,6 +70,18 @@ impl fmt::Display for BiField {
             BiField::SignedInteger { name, value } => {
                 write!(f, ":s {} {}", String::from_utf8_lossy(name), value)
             }
+            BiField::BigInteger { name, value } => {
+                write!(f, ":i {} {}", String::from_utf8_lossy(name), value)
+            }
+            BiField::BigSignedInteger { name, value } => {
+                write!(f, ":s {} {}", String::from_utf8_lossy(name), value)
+            }
+            BiField::Float { name, value } => {
+                write!(f, ":f {} {:?}", String::from_utf8_lossy(name), value)
+            }
+            BiField::Bool { name, value } => {
+                write!(f, ":t {} {}", String::from_utf8_lossy(name), value)
+            }
             BiField::Blob { name, data } => {
                 write!(f, ":b {} {}", String::from_utf8_lossy(name), data.len())
             }
@@ -33,22 +89,142 @@ impl fmt::Display for BiField {
     }
 }
 
+/// An integer of arbitrary width, stored as its decimal text form.
+///
+/// The text is kept exactly as it appeared in the input (including any leading sign or zeros) so
+/// values round-trip without loss. Equality compares the text, so `007` and `7` are not equal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    text: Vec<u8>,
+}
+
+impl BigInt {
+    /// Parses an optionally signed sequence of ASCII digits.
+    ///
+    /// ### Parameters
+    /// - `bytes`: Decimal text of the integer.
+    pub fn parse(bytes: &[u8]) -> Result<Self, BiValidationError> {
+        validate_big_signed_integer(bytes)?;
+        Ok(Self {
+            text: bytes.to_vec(),
+        })
+    }
+
+    /// Whether the value has a leading `-` sign. Note `-0` is considered negative.
+    pub fn is_negative(&self) -> bool {
+        self.text.first() == Some(&b'-')
+    }
+
+    /// The decimal text of the value, including any sign.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.text
+    }
+
+    /// The decimal digits of the value, without any sign.
+    pub fn digits(&self) -> &[u8] {
+        match self.text.first() {
+            Some(b'-') | Some(b'+') => &self.text[1..],
+            _ => &self.text,
+        }
+    }
+
+    /// Converts the value to a `u128`, if it fits.
+    pub fn to_u128(&self) -> Option<u128> {
+        core::str::from_utf8(&self.text).ok()?.parse().ok()
+    }
+
+    /// Converts the value to an `i128`, if it fits.
+    pub fn to_i128(&self) -> Option<i128> {
+        core::str::from_utf8(&self.text).ok()?.parse().ok()
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The text is validated as ASCII on construction.
+        f.write_str(&String::from_utf8_lossy(&self.text))
+    }
+}
+
+macro_rules! impl_big_int_from {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for BigInt {
+                fn from(value: $t) -> Self {
+                    Self {
+                        text: value.to_string().into_bytes(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_big_int_from!(u64, i64, u128, i128);
+
 /// Type of field marker.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FieldMarker {
     Integer,
     SignedInteger,
+    Float,
+    Bool,
     Blob,
+    /// Blob with gzip compressed content, read back as a [`BiField::Blob`] holding the original
+    /// bytes.
+    GzipBlob,
 }
 
 impl FieldMarker {
-    /// Converts a byte following the `:` symbol into the corresponding marker type.
+    /// Converts a byte following the `:` symbol into the corresponding marker type. Recognizes
+    /// every marker known to the crate, use [`Dialect::supports`] to check whether the marker is
+    /// allowed in a given dialect.
     pub fn from_byte(byte: u8) -> Option<Self> {
         match byte {
-            crate::bi_core::MARKER_INT => Some(FieldMarker::Integer),
-            crate::bi_core::MARKER_SINT => Some(FieldMarker::SignedInteger),
-            crate::bi_core::MARKER_BLOB => Some(FieldMarker::Blob),
+            MARKER_INT => Some(FieldMarker::Integer),
+            MARKER_SINT => Some(FieldMarker::SignedInteger),
+            MARKER_FLOAT => Some(FieldMarker::Float),
+            MARKER_BOOL => Some(FieldMarker::Bool),
+            MARKER_BLOB => Some(FieldMarker::Blob),
+            MARKER_GZIP_BLOB => Some(FieldMarker::GzipBlob),
             _ => None,
         }
     }
+
+    /// Converts the marker type into the byte following the `:` symbol.
+    pub fn to_byte(&self) -> u8 {
+        match self {
+            FieldMarker::Integer => MARKER_INT,
+            FieldMarker::SignedInteger => MARKER_SINT,
+            FieldMarker::Float => MARKER_FLOAT,
+            FieldMarker::Bool => MARKER_BOOL,
+            FieldMarker::Blob => MARKER_BLOB,
+            FieldMarker::GzipBlob => MARKER_GZIP_BLOB,
+        }
+    }
+}
+
+/// Set of field markers accepted by a reader or writer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// The original bi format specification, `:i` and `:b` fields only.
+    Strict,
+    /// The original specification plus the signed integer (`:s`) and compressed blob (`:z`)
+    /// extensions used by rere snapshots.
+    #[default]
+    Signed,
+    /// All markers supported by this crate, adding floating point (`:f`) and boolean (`:t`)
+    /// fields on top of [`Dialect::Signed`].
+    Extended,
+}
+
+impl Dialect {
+    /// Whether the marker type is allowed in this dialect.
+    pub fn supports(&self, marker: &FieldMarker) -> bool {
+        match marker {
+            FieldMarker::Integer | FieldMarker::Blob => true,
+            FieldMarker::SignedInteger | FieldMarker::GzipBlob => !matches!(self, Dialect::Strict),
+            FieldMarker::Float | FieldMarker::Bool => matches!(self, Dialect::Extended),
+        }
+    }
 }
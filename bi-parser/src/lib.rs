@@ -7,10 +7,17 @@
 //! - Parse bi format files with validation
 //! - Write bi format files
 //! - Support for both Integer and Blob fields
+//! - Optional extended dialect with Float and Bool fields
 //! - Comprehensive error handling
+//! - Optional trailing CRC-32 checksum field, written and verified automatically
+//! - Whole-file linting that collects every problem with its position
+//! - `no_std` + `alloc` support for the core types, validation, and slice based parsing and
+//!   serialization (disable the default `std` feature)
 //!
 //! ## Quickstart
-//! ```no_run
+//! `BiReader` and `BiWriter` need the default `std` feature.
+#![cfg_attr(feature = "std", doc = "```no_run")]
+#![cfg_attr(not(feature = "std"), doc = "```ignore")]
 //! use bi_parser::prelude::*;
 //! use std::fs::File;
 //!
@@ -29,15 +36,32 @@
 //! writer.write_field_default(&field).unwrap();
 //! ```
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod bi_core;
 pub mod parser;
 pub mod validator;
 pub mod writer;
 
 pub mod prelude {
+    pub use crate::bi_core::checksum::{ChecksumPolicy, Crc32, CHECKSUM_NAME};
     pub use crate::bi_core::error::BiError;
-    pub use crate::bi_core::types::BiField;
-    pub use crate::bi_core::{MARKER_BLOB, MARKER_INT, MARKER_SINT, MARKER_SYM, NEWLINE, SPACE};
+    pub use crate::bi_core::types::{BiField, BigInt, Dialect};
+    pub use crate::bi_core::{
+        MARKER_BLOB, MARKER_BOOL, MARKER_FLOAT, MARKER_GZIP_BLOB, MARKER_INT, MARKER_SINT,
+        MARKER_SYM, NEWLINE, SPACE,
+    };
+    #[cfg(feature = "std")]
     pub use crate::parser::reader::BiReader;
+    pub use crate::parser::slice::SliceReader;
+    #[cfg(feature = "std")]
+    pub use crate::validator::lint::validate_reader;
+    pub use crate::validator::lint::{
+        validate_stream, Diagnostic, DiagnosticKind, DuplicateNames, LintOptions, Severity,
+    };
+    pub use crate::writer::serialize::{serialize_field, serialize_field_default};
+    #[cfg(feature = "std")]
     pub use crate::writer::writer::BiWriter;
 }
@@ -0,0 +1,224 @@
+//! # Decode Module
+//!
+//! Shared decoding of field pieces into `BiField`s, used by both the streaming `BiReader` and the
+//! slice based `SliceReader`.
+
+use super::error::BiParserError;
+use crate::bi_core::types::{BiField, BigInt, Dialect, FieldMarker};
+use crate::validator::error::BiValidationError;
+use crate::validator::utils::{
+    validate_big_integer, validate_big_signed_integer, validate_blob, validate_bool,
+    validate_field_name, validate_float, validate_marker,
+};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Validates the 3-byte marker and resolves its type against the dialect.
+pub(crate) fn decode_marker(
+    marker: [u8; 3],
+    validate: bool,
+    dialect: Dialect,
+) -> Result<FieldMarker, BiParserError> {
+    if validate {
+        validate_marker(marker, false)?;
+    }
+    FieldMarker::from_byte(marker[1])
+        .filter(|m| dialect.supports(m))
+        .ok_or(BiParserError::ValidationError(
+            BiValidationError::InvalidMarkerType(marker[1] as char),
+        ))
+}
+
+/// Validates the field name bytes (without the trailing space).
+pub(crate) fn decode_name(name: &[u8], validate: bool) -> Result<(), BiParserError> {
+    if validate {
+        validate_field_name(name)?;
+    }
+    Ok(())
+}
+
+/// Decodes the value line (without the trailing newline) of any non-blob field.
+pub(crate) fn decode_scalar(
+    marker: &FieldMarker,
+    name: Vec<u8>,
+    value_bytes: &[u8],
+    validate: bool,
+) -> Result<BiField, BiParserError> {
+    match marker {
+        // Like other values, integers are validated first when asked to. Parsing rejects malformed
+        // digits either way, and only values that don't fit take the slower `BigInt` path.
+        FieldMarker::Integer => {
+            if validate {
+                validate_big_integer(value_bytes)?;
+            }
+            match parse_u64(value_bytes) {
+                Some(value) => Ok(BiField::Integer { name, value }),
+                None => {
+                    let value = BigInt::parse(value_bytes)
+                        .ok()
+                        .filter(|v| !v.is_negative())
+                        .ok_or_else(|| invalid_integer(value_bytes))?;
+                    Ok(BiField::BigInteger { name, value })
+                }
+            }
+        }
+        FieldMarker::SignedInteger => {
+            if validate {
+                validate_big_signed_integer(value_bytes)?;
+            }
+            match parse_i64(value_bytes) {
+                Some(value) => Ok(BiField::SignedInteger { name, value }),
+                None => {
+                    let value =
+                        BigInt::parse(value_bytes).map_err(|_| invalid_integer(value_bytes))?;
+                    Ok(BiField::BigSignedInteger { name, value })
+                }
+            }
+        }
+        FieldMarker::Float => {
+            if validate {
+                validate_float(value_bytes)?;
+            }
+            let value_str = utf8(value_bytes)?;
+            let value = value_str
+                .parse::<f64>()
+                .map_err(|_| BiValidationError::InvalidFloat(value_str.into()))?;
+            Ok(BiField::Float { name, value })
+        }
+        FieldMarker::Bool => {
+            if validate {
+                validate_bool(value_bytes)?;
+            }
+            let value = match value_bytes {
+                b"true" => true,
+                b"false" => false,
+                _ => {
+                    return Err(BiValidationError::InvalidBool(
+                        String::from_utf8_lossy(value_bytes).into_owned(),
+                    )
+                    .into())
+                }
+            };
+            Ok(BiField::Bool { name, value })
+        }
+        FieldMarker::Blob | FieldMarker::GzipBlob => {
+            unreachable!("blob fields are decoded with `decode_blob`")
+        }
+    }
+}
+
+/// Decodes the size line (without the trailing newline) of a blob field.
+pub(crate) fn decode_blob_size(size_bytes: &[u8]) -> Result<usize, BiParserError> {
+    parse_u64(size_bytes)
+        .and_then(|size| usize::try_from(size).ok())
+        .ok_or_else(|| invalid_integer(size_bytes).into())
+}
+
+/// Decodes blob content, `data` is expected to hold the content plus the trailing newline.
+pub(crate) fn decode_blob(
+    name: Vec<u8>,
+    mut data: Vec<u8>,
+    size: usize,
+    validate: bool,
+) -> Result<BiField, BiParserError> {
+    if validate {
+        validate_blob(&data, size)?;
+    }
+    data.pop();
+    Ok(BiField::Blob { name, data })
+}
+
+/// Decompresses the content of a blob decoded from a `:z` field, failing once it grows past
+/// `limit` bytes.
+pub(crate) fn decode_gzip_blob(field: BiField, limit: usize) -> Result<BiField, BiParserError> {
+    let BiField::Blob { name, data } = field else {
+        unreachable!("`:z` fields are decoded with `decode_blob`")
+    };
+    #[cfg(feature = "compression")]
+    let result = crate::bi_core::compression::decompress(&data, limit).map_err(|e| e.to_string());
+    #[cfg(not(feature = "compression"))]
+    let result = {
+        let _ = (data, limit);
+        Err(String::from("the `compression` feature is disabled"))
+    };
+    match result {
+        Ok(data) => Ok(BiField::Blob { name, data }),
+        Err(e) => Err(BiParserError::DecompressionError(format!(
+            "`{}`: {}",
+            String::from_utf8_lossy(&name),
+            e
+        ))),
+    }
+}
+
+/// Parses ASCII digits, with an optional leading `+`, into a `u64`. Returns `None` if the bytes
+/// are empty, contain anything else, or overflow.
+fn parse_u64(bytes: &[u8]) -> Option<u64> {
+    let digits = bytes.strip_prefix(b"+").unwrap_or(bytes);
+    if digits.is_empty() {
+        return None;
+    }
+    digits.iter().try_fold(0u64, |acc, b| {
+        let digit = b.wrapping_sub(b'0');
+        if digit > 9 {
+            return None;
+        }
+        acc.checked_mul(10)?.checked_add(digit as u64)
+    })
+}
+
+/// Parses ASCII digits, with an optional leading sign, into an `i64`. Returns `None` if the bytes
+/// are empty, contain anything else, or overflow.
+fn parse_i64(bytes: &[u8]) -> Option<i64> {
+    match bytes.strip_prefix(b"-") {
+        Some(digits) if !digits.starts_with(b"+") => 0i64.checked_sub_unsigned(parse_u64(digits)?),
+        Some(_) => None,
+        None => i64::try_from(parse_u64(bytes)?).ok(),
+    }
+}
+
+fn invalid_integer(bytes: &[u8]) -> BiValidationError {
+    BiValidationError::InvalidInteger(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn utf8(bytes: &[u8]) -> Result<&str, BiParserError> {
+    Ok(core::str::from_utf8(bytes).map_err(BiValidationError::Utf8Error)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_integers() {
+        assert_eq!(parse_u64(b"0"), Some(0));
+        assert_eq!(parse_u64(b"+42"), Some(42));
+        assert_eq!(parse_u64(b"18446744073709551615"), Some(u64::MAX));
+        assert_eq!(parse_u64(b"18446744073709551616"), None);
+        assert_eq!(parse_u64(b""), None);
+        assert_eq!(parse_u64(b"+"), None);
+        assert_eq!(parse_u64(b"-1"), None);
+        assert_eq!(parse_u64(b"1a"), None);
+
+        assert_eq!(parse_i64(b"-9223372036854775808"), Some(i64::MIN));
+        assert_eq!(parse_i64(b"9223372036854775807"), Some(i64::MAX));
+        assert_eq!(parse_i64(b"9223372036854775808"), None);
+        assert_eq!(parse_i64(b"-9223372036854775809"), None);
+        assert_eq!(parse_i64(b"-0"), Some(0));
+        assert_eq!(parse_i64(b"-+1"), None);
+        assert_eq!(parse_i64(b"--1"), None);
+    }
+
+    #[test]
+    fn test_double_sign() {
+        for value in [&b"-+1"[..], b"+-1"] {
+            for validate in [false, true] {
+                let decode = |marker| decode_scalar(&marker, b"x".to_vec(), value, validate);
+                assert!(decode(FieldMarker::SignedInteger).is_err());
+                assert!(decode(FieldMarker::Integer).is_err());
+            }
+            assert!(BigInt::parse(value).is_err());
+        }
+    }
+}
@@ -3,6 +3,7 @@
 //! This module provides the parser error type.
 
 use crate::validator::error::BiValidationError;
+use alloc::string::String;
 use thiserror::Error;
 
 /// Error that occurs during parsing of bi format files.
@@ -16,6 +17,23 @@ pub enum BiParserError {
     #[error("Error reading input: {0}")]
     ReadError(String),
 
+    /// Checksum field doesn't match the content before it.
+    #[error("Checksum mismatch: expected {expected:#010x}, content has {actual:#010x}")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+
+    /// Checksum field isn't an integer that fits a CRC-32.
+    #[error("Invalid checksum: {0}")]
+    InvalidChecksum(String),
+
+    /// Stream ended without a checksum field while one is required.
+    #[error("Missing checksum: stream doesn't end with a checksum field")]
+    MissingChecksum,
+
+    /// Compressed blob content is not a valid gzip stream, is larger than allowed, or can't be
+    /// decompressed because the `compression` feature is disabled.
+    #[error("Error decompressing blob: {0}")]
+    DecompressionError(String),
+
     /// Field validation failed.
     #[error(transparent)]
     ValidationError(#[from] BiValidationError),
@@ -5,7 +5,9 @@
 //! found [here](https://github.com/tsoding/bi-format/tree/main?tab=readme-ov-file)).
 //!
 //! ## Examples
-//! ```no_run
+//! Files are read with the `BiReader`, which needs the default `std` feature:
+#![cfg_attr(feature = "std", doc = "```no_run")]
+#![cfg_attr(not(feature = "std"), doc = "```ignore")]
 //! use bi_parser::parser::reader::BiReader;
 //! use std::fs::File;
 //!
@@ -14,6 +16,18 @@
 //! // Reads a single field from `test.bi`
 //! let field = reader.read_field_default().unwrap();
 //! ```
+//!
+//! Data already in memory can be parsed with the `SliceReader`, which is also available without
+//! the `std` feature:
+//! ```
+//! use bi_parser::parser::slice::SliceReader;
+//!
+//! let mut reader = SliceReader::new(b":i count 42\n");
+//! let field = reader.read_field_default().unwrap();
+//! ```
 
+pub(crate) mod decode;
 pub mod error;
+#[cfg(feature = "std")]
 pub mod reader;
+pub mod slice;
@@ -2,19 +2,33 @@
 //!
 //! Provides the `BiReader`.
 
+use super::decode::{
+    decode_blob, decode_blob_size, decode_gzip_blob, decode_marker, decode_name, decode_scalar,
+};
 use super::error::BiParserError;
-use crate::bi_core::types::FieldMarker;
-use crate::bi_core::{NEWLINE, SPACE};
+use crate::bi_core::checksum::{ChecksumPolicy, Crc32, CHECKSUM_NAME};
+use crate::bi_core::types::{Dialect, FieldMarker};
+use crate::bi_core::{DEFAULT_DECOMPRESSION_LIMIT, NEWLINE, SPACE};
 use crate::prelude::{BiError, BiField};
-use crate::validator::error::BiValidationError;
-use crate::validator::utils::{
-    validate_blob, validate_field_name, validate_integer, validate_marker, validate_signed_integer,
-};
 use std::io::{BufRead, BufReader, Read};
 
+/// Upper bound on the up front allocation for blob content, larger blobs grow as they're read so
+/// a corrupt size can't trigger a huge allocation.
+const BLOB_PREALLOC_LIMIT: usize = 1 << 20;
+
 /// A buffered reader for parsing bi format files.
 pub struct BiReader<R> {
     reader: BufReader<R>,
+    dialect: Dialect,
+    /// Reused buffer for value and blob size lines.
+    scratch: Vec<u8>,
+    checksum: ChecksumPolicy,
+    /// Digest of the bytes read since the start or the last checksum field.
+    crc: Crc32,
+    /// Whether fields were read since the start or the last checksum field.
+    unchecked: bool,
+    /// Maximum decompressed size of a `:z` blob.
+    decompression_limit: usize,
 }
 
 impl<R: Read> BiReader<R> {
@@ -22,9 +36,58 @@ impl<R: Read> BiReader<R> {
     pub fn new(reader: R) -> Self {
         Self {
             reader: BufReader::new(reader),
+            dialect: Dialect::default(),
+            scratch: Vec::new(),
+            checksum: ChecksumPolicy::default(),
+            crc: Crc32::new(),
+            unchecked: false,
+            decompression_limit: DEFAULT_DECOMPRESSION_LIMIT,
         }
     }
 
+    /// Returns the underlying reader. Any data buffered but not yet parsed is lost.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    /// Sets the dialect of field markers the reader accepts (defaults to [`Dialect::Signed`]).
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Sets how checksum fields are treated (defaults to [`ChecksumPolicy::Ignore`]). See
+    /// [`crate::bi_core::checksum`] for the convention.
+    pub fn with_checksum(mut self, checksum: ChecksumPolicy) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Sets the maximum decompressed size of a `:z` blob, larger content fails with
+    /// `BiParserError::DecompressionError` (defaults to [`DEFAULT_DECOMPRESSION_LIMIT`]). See
+    /// [`crate::bi_core::compression`].
+    #[cfg(feature = "compression")]
+    pub fn with_decompression_limit(mut self, limit: usize) -> Self {
+        self.decompression_limit = limit;
+        self
+    }
+
+    /// Whether the underlying reader has no more data, i.e. the previous field was the last one.
+    ///
+    /// With [`ChecksumPolicy::Require`], reaching the end with fields that weren't covered by a
+    /// checksum field is a `BiParserError::MissingChecksum` error.
+    pub fn is_eof(&mut self) -> Result<bool, BiError> {
+        let buf = self
+            .reader
+            .fill_buf()
+            .map_err(|e| BiParserError::ReadError(e.to_string()))?;
+        let eof = buf.is_empty();
+        if eof && self.checksum == ChecksumPolicy::Require && self.unchecked {
+            return Err(BiParserError::MissingChecksum.into());
+        }
+        Ok(eof)
+    }
+
     /// Read a field with validation enabled (default behavior).
     ///
     /// Equivalent to `read_field(true)`.
@@ -34,9 +97,13 @@ impl<R: Read> BiReader<R> {
 
     /// Read a single field from the underlying reader.
     ///
+    /// Unless checksums are ignored, a checksum field is verified against the fields read before
+    /// it and returned like any other field. Compressed `:z` blobs are returned decompressed, as
+    /// [`BiField::Blob`]s.
+    ///
     /// ### Parameters
     /// - `validate`: Whether or not to perform validation that the parsed data conforms to the bi
-    /// format specification.
+    ///   format specification.
     ///
     /// ### Returns
     /// `Result<BiField, BiError>`
@@ -49,135 +116,146 @@ impl<R: Read> BiReader<R> {
                 std::io::ErrorKind::UnexpectedEof => {
                     BiParserError::UnexpectedEof("while reading marker".to_string())
                 }
-                _ => BiParserError::ReadError(e.to_string()).into(),
+                _ => BiParserError::ReadError(e.to_string()),
             })?;
-        if validate {
-            validate_marker(marker, false).map_err(|e| BiParserError::ValidationError(e))?;
-        }
-        // Extract and validate the marker type.
-        let marker_type = FieldMarker::from_byte(marker[1]).ok_or_else(|| {
-            BiParserError::ValidationError(BiValidationError::InvalidMarkerType(marker[1] as char))
-        })?;
+        let marker_type = decode_marker(marker, validate, self.dialect)?;
+        let gzip = marker_type == FieldMarker::GzipBlob;
+        let crc = self.crc;
+        self.digest(&marker);
 
         // Read the field name up to the next space.
         let mut name_bytes = Vec::new();
-        self.reader
-            .read_until(SPACE, &mut name_bytes)
-            .map_err(|e| {
-                BiParserError::ReadError(format!(
-                    "error when reading field name: {}",
-                    e.to_string()
-                ))
-            })?;
-        // Remove trailing space.
-        name_bytes.pop();
-        if validate {
-            validate_field_name(&name_bytes).map_err(|e| BiParserError::ValidationError(e))?;
+        self.read_until(SPACE, "field name", &mut name_bytes)?;
+        decode_name(&name_bytes, validate)?;
+        self.digest(&name_bytes);
+        self.digest(&[SPACE]);
+
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        let result = match marker_type {
+            FieldMarker::Blob | FieldMarker::GzipBlob => {
+                self.read_blob(name_bytes, &mut scratch, validate)
+            }
+            marker_type => self
+                .read_until(NEWLINE, "field value", &mut scratch)
+                .and_then(|_| {
+                    self.digest(&scratch);
+                    self.digest(&[NEWLINE]);
+                    decode_scalar(&marker_type, name_bytes, &scratch, validate)
+                }),
+        };
+        self.scratch = scratch;
+        let field = result?;
+
+        if self.checksum != ChecksumPolicy::Ignore {
+            self.check_field(&field, crc)?;
         }
+        if gzip {
+            return Ok(decode_gzip_blob(field, self.decompression_limit)?);
+        }
+        Ok(field)
+    }
 
-        match marker_type {
-            FieldMarker::Integer => {
-                let mut value_bytes = Vec::new();
-                self.reader
-                    .read_until(NEWLINE, &mut value_bytes)
-                    .map_err(|e| {
-                        BiParserError::ReadError(format!(
-                            "error reading integer field value: {}",
-                            e.to_string()
-                        ))
-                    })?;
-                value_bytes.pop();
-                if validate {
-                    validate_integer(&value_bytes)
-                        .map_err(|e| BiParserError::ValidationError(e))?;
-                }
-
-                let value_str = String::from_utf8(value_bytes).map_err(|e| {
-                    BiParserError::ValidationError(BiValidationError::Utf8Error(e.utf8_error()))
-                })?;
-                let value = value_str
-                    .parse::<u64>()
-                    .map_err(|_| BiValidationError::InvalidInteger(value_str))?;
-
-                Ok(BiField::Integer {
-                    name: name_bytes,
-                    value,
-                })
+    /// Verifies `field` if it's a checksum field, `crc` being the digest before it.
+    fn check_field(&mut self, field: &BiField, crc: Crc32) -> Result<(), BiParserError> {
+        if field.name() != CHECKSUM_NAME {
+            self.unchecked = true;
+            return Ok(());
+        }
+        let expected = match field {
+            BiField::Integer { value, .. } => u32::try_from(*value).map_err(|_| {
+                BiParserError::InvalidChecksum(format!("{value} is out of range for a CRC-32"))
+            })?,
+            BiField::BigInteger { value, .. } => {
+                return Err(BiParserError::InvalidChecksum(format!(
+                    "{value} is out of range for a CRC-32"
+                )))
             }
-            FieldMarker::SignedInteger => {
-                let mut value_bytes = Vec::new();
-                self.reader
-                    .read_until(NEWLINE, &mut value_bytes)
-                    .map_err(|e| {
-                        BiParserError::ReadError(format!(
-                            "error reading signed integer field value: {}",
-                            e.to_string()
-                        ))
-                    })?;
-                value_bytes.pop();
-                if validate {
-                    validate_signed_integer(&value_bytes)
-                        .map_err(|e| BiParserError::ValidationError(e))?;
-                }
-
-                let value_str = String::from_utf8(value_bytes).map_err(|e| {
-                    BiParserError::ValidationError(BiValidationError::Utf8Error(e.utf8_error()))
-                })?;
-                let value = value_str
-                    .parse::<i64>()
-                    .map_err(|_| BiValidationError::InvalidInteger(value_str))?;
-
-                Ok(BiField::SignedInteger {
-                    name: name_bytes,
-                    value,
-                })
+            field => {
+                return Err(BiParserError::InvalidChecksum(format!(
+                    "expected an `:i` field, found `:{}`",
+                    field.marker().to_byte() as char
+                )))
             }
-            FieldMarker::Blob => {
-                let mut size_bytes = Vec::new();
-                self.reader
-                    .read_until(NEWLINE, &mut size_bytes)
-                    .map_err(|e| {
-                        BiParserError::ReadError(format!(
-                            "error reading blob size: {}",
-                            e.to_string()
-                        ))
-                    })?;
-                size_bytes.pop();
-                if validate {
-                    validate_integer(&size_bytes).map_err(|e| BiParserError::ValidationError(e))?;
-                }
+        };
+        if expected != crc.value() {
+            return Err(BiParserError::ChecksumMismatch {
+                expected,
+                actual: crc.value(),
+            });
+        }
+        self.crc = Crc32::new();
+        self.unchecked = false;
+        Ok(())
+    }
 
-                let size_str = String::from_utf8(size_bytes)
-                    .map_err(|e| BiValidationError::Utf8Error(e.utf8_error()))?;
-                let size = size_str
-                    .parse::<usize>()
-                    .map_err(|_| BiValidationError::InvalidInteger(size_str))?;
-
-                let mut data = vec![0; size + 1];
-                self.reader.read_exact(&mut data).map_err(|e| {
-                    BiParserError::ReadError(format!(
-                        "error reading blob content: {}",
-                        e.to_string()
-                    ))
-                })?;
-
-                if validate {
-                    validate_blob(&data, size).map_err(|e| BiParserError::ValidationError(e))?;
-                }
-                data.pop();
+    /// Feeds bytes that were read into the running checksum.
+    fn digest(&mut self, bytes: &[u8]) {
+        if self.checksum != ChecksumPolicy::Ignore {
+            self.crc.update(bytes);
+        }
+    }
 
-                Ok(BiField::Blob {
-                    name: name_bytes,
-                    data,
-                })
-            }
+    /// Reads the size line and content of a blob field.
+    fn read_blob(
+        &mut self,
+        name: Vec<u8>,
+        scratch: &mut Vec<u8>,
+        validate: bool,
+    ) -> Result<BiField, BiParserError> {
+        self.read_until(NEWLINE, "blob size", scratch)?;
+        let size = decode_blob_size(scratch)?;
+        self.digest(scratch);
+        self.digest(&[NEWLINE]);
+
+        // Content plus the trailing newline.
+        let len = size.saturating_add(1);
+        let mut data = Vec::with_capacity(len.min(BLOB_PREALLOC_LIMIT));
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut data)
+            .map_err(|e| BiParserError::ReadError(format!("error reading blob content: {}", e)))?;
+        if data.len() != len {
+            return Err(BiParserError::ReadError(
+                "error reading blob content: failed to fill whole buffer".to_string(),
+            ));
         }
+        self.digest(&data);
+
+        decode_blob(name, data, size, validate)
+    }
+
+    /// Appends bytes up to and including the next `delimiter` to `bytes`, without the delimiter.
+    ///
+    /// ### Parameters
+    /// - `delimiter`: Byte terminating the value.
+    /// - `what`: Description of the value being read, used in error messages.
+    /// - `bytes`: Buffer the value is appended to.
+    fn read_until(
+        &mut self,
+        delimiter: u8,
+        what: &str,
+        bytes: &mut Vec<u8>,
+    ) -> Result<(), BiParserError> {
+        self.reader
+            .read_until(delimiter, bytes)
+            .map_err(|e| BiParserError::ReadError(format!("error reading {}: {}", what, e)))?;
+        if bytes.pop() != Some(delimiter) {
+            return Err(BiParserError::UnexpectedEof(format!(
+                "while reading {}",
+                what
+            )));
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bi_core::checksum::{ChecksumPolicy, CHECKSUM_NAME};
+    use crate::prelude::{BiWriter, SliceReader};
+    use crate::validator::error::BiValidationError;
     use std::io::Cursor;
 
     fn create_reader(content: &str) -> BiReader<Cursor<Vec<u8>>> {
@@ -226,6 +304,119 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_read_big_integer_fields() {
+        let mut reader = create_reader(
+            ":i max 18446744073709551615\n\
+             :i hash 340282366920938463463374607431768211456\n\
+             :s low -170141183460469231731687303715884105728\n",
+        );
+        assert_eq!(
+            reader.read_field_default().unwrap(),
+            BiField::Integer {
+                name: b"max".to_vec(),
+                value: u64::MAX
+            }
+        );
+
+        match reader.read_field_default().unwrap() {
+            BiField::BigInteger { name, value } => {
+                assert_eq!(name, b"hash");
+                assert_eq!(value.as_bytes(), b"340282366920938463463374607431768211456");
+                assert_eq!(value.to_u128(), None);
+            }
+            _ => panic!("Expected big integer field"),
+        }
+
+        match reader.read_field_default().unwrap() {
+            BiField::BigSignedInteger { name, value } => {
+                assert_eq!(name, b"low");
+                assert_eq!(value.to_i128(), Some(i128::MIN));
+            }
+            _ => panic!("Expected big signed integer field"),
+        }
+    }
+
+    #[test]
+    fn test_read_float_field() {
+        let mut reader = create_reader(":f ratio -0.125\n").with_dialect(Dialect::Extended);
+        let field = reader.read_field_default().unwrap();
+
+        match field {
+            BiField::Float { name, value } => {
+                assert_eq!(name, b"ratio");
+                assert_eq!(value, -0.125);
+            }
+            _ => panic!("Expected float field"),
+        }
+    }
+
+    #[test]
+    fn test_read_bool_field() {
+        let mut reader =
+            create_reader(":t ok true\n:t done false\n").with_dialect(Dialect::Extended);
+        assert_eq!(
+            reader.read_field_default().unwrap(),
+            BiField::Bool {
+                name: b"ok".to_vec(),
+                value: true
+            }
+        );
+        assert_eq!(
+            reader.read_field_default().unwrap(),
+            BiField::Bool {
+                name: b"done".to_vec(),
+                value: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_extended_markers_require_dialect() {
+        let mut reader = create_reader(":f ratio 0.5\n");
+        assert!(matches!(
+            reader.read_field_default().unwrap_err(),
+            BiError::ParseError(BiParserError::ValidationError(
+                BiValidationError::InvalidMarkerType('f')
+            ))
+        ));
+
+        let mut reader = create_reader(":s count -42\n").with_dialect(Dialect::Strict);
+        assert!(matches!(
+            reader.read_field_default().unwrap_err(),
+            BiError::ParseError(BiParserError::ValidationError(
+                BiValidationError::InvalidMarkerType('s')
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_float_and_bool_values() {
+        let mut reader = create_reader(":f ratio 1.2.3\n").with_dialect(Dialect::Extended);
+        assert!(matches!(
+            reader.read_field_default().unwrap_err(),
+            BiError::ParseError(BiParserError::ValidationError(
+                BiValidationError::InvalidFloat(_)
+            ))
+        ));
+
+        let mut reader = create_reader(":t ok yes\n").with_dialect(Dialect::Extended);
+        assert!(matches!(
+            reader.read_field_default().unwrap_err(),
+            BiError::ParseError(BiParserError::ValidationError(
+                BiValidationError::InvalidBool(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_is_eof() {
+        let mut reader = create_reader(":i count 42\n");
+        assert!(!reader.is_eof().unwrap());
+        reader.read_field_default().unwrap();
+        assert!(reader.is_eof().unwrap());
+    }
+
     #[test]
     fn test_invalid_marker() {
         let mut reader = create_reader("#i count 42\n");
@@ -322,4 +513,146 @@ mod tests {
             ))
         ));
     }
+
+    fn checksummed() -> Vec<u8> {
+        let mut writer = BiWriter::new(Vec::new()).with_checksum(true);
+        writer
+            .write_field_default(&BiField::Integer {
+                name: b"count".to_vec(),
+                value: 1,
+            })
+            .unwrap();
+        writer
+            .write_field_default(&BiField::Blob {
+                name: b"stdout".to_vec(),
+                data: b"hello\nworld".to_vec(),
+            })
+            .unwrap();
+        writer.finish().unwrap()
+    }
+
+    fn read_all(bytes: &[u8], checksum: ChecksumPolicy) -> Result<Vec<BiField>, BiError> {
+        let mut reader = BiReader::new(bytes).with_checksum(checksum);
+        let mut fields = Vec::new();
+        while !reader.is_eof()? {
+            fields.push(reader.read_field_default()?);
+        }
+        Ok(fields)
+    }
+
+    #[test]
+    fn test_checksum_verified() {
+        let bytes = checksummed();
+        let fields = read_all(&bytes, ChecksumPolicy::Require).unwrap();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[2].name(), CHECKSUM_NAME);
+
+        // Two checksummed streams back to back verify independently.
+        let twice = [bytes.clone(), bytes].concat();
+        assert_eq!(read_all(&twice, ChecksumPolicy::Require).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        // Same blob size, different content.
+        let corrupted = String::from_utf8(checksummed())
+            .unwrap()
+            .replace("world", "w0rld");
+        assert!(matches!(
+            read_all(corrupted.as_bytes(), ChecksumPolicy::Verify).unwrap_err(),
+            BiError::ParseError(BiParserError::ChecksumMismatch { .. })
+        ));
+        assert!(read_all(corrupted.as_bytes(), ChecksumPolicy::Ignore).is_ok());
+    }
+
+    #[test]
+    fn test_checksum_invalid() {
+        for bytes in [
+            ":i bi.crc32 4294967296\n",
+            ":i bi.crc32 18446744073709551616\n",
+            ":s bi.crc32 -1\n",
+            ":b bi.crc32 1\n0\n",
+        ] {
+            assert!(
+                matches!(
+                    read_all(bytes.as_bytes(), ChecksumPolicy::Verify).unwrap_err(),
+                    BiError::ParseError(BiParserError::InvalidChecksum(_))
+                ),
+                "{bytes:?}"
+            );
+            assert!(read_all(bytes.as_bytes(), ChecksumPolicy::Ignore).is_ok());
+        }
+        // In range, so it's compared against the content (the CRC-32 of nothing is 0).
+        assert!(read_all(b":i bi.crc32 0\n", ChecksumPolicy::Require).is_ok());
+        assert!(matches!(
+            read_all(b":i bi.crc32 4294967295\n", ChecksumPolicy::Verify).unwrap_err(),
+            BiError::ParseError(BiParserError::ChecksumMismatch {
+                expected: u32::MAX,
+                actual: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_checksum_missing() {
+        let bytes = b":i count 1\n";
+        assert!(read_all(bytes, ChecksumPolicy::Verify).is_ok());
+        assert!(matches!(
+            read_all(bytes, ChecksumPolicy::Require).unwrap_err(),
+            BiError::ParseError(BiParserError::MissingChecksum)
+        ));
+        assert!(read_all(b"", ChecksumPolicy::Require).is_ok());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_blobs() {
+        let log = b"INFO all good\n".repeat(500);
+        let fields = vec![
+            BiField::Blob {
+                name: b"stdout".to_vec(),
+                data: log.clone(),
+            },
+            BiField::Blob {
+                name: b"stderr".to_vec(),
+                data: b"short".to_vec(),
+            },
+            BiField::Blob {
+                name: b"archive.gz".to_vec(),
+                data: b"already suffixed".to_vec(),
+            },
+        ];
+        let mut writer = BiWriter::new(Vec::new()).with_compression(64);
+        for field in &fields {
+            writer.write_field_default(field).unwrap();
+        }
+        let bytes = writer.into_inner().unwrap();
+        assert!(bytes.len() < log.len() / 10);
+        assert!(bytes.starts_with(b":z stdout "));
+        // Compression is marked by the `:z` marker alone, names are left as is.
+        assert!(bytes.ends_with(b":b archive.gz 16\nalready suffixed\n"));
+
+        let mut reader = BiReader::new(bytes.as_slice());
+        for field in &fields {
+            assert_eq!(&reader.read_field_default().unwrap(), field);
+        }
+        let mut reader = SliceReader::new(&bytes);
+        assert_eq!(&reader.read_field_default().unwrap(), &fields[0]);
+
+        let mut reader = BiReader::new(bytes.as_slice()).with_decompression_limit(log.len() - 1);
+        let error = reader.read_field_default().unwrap_err();
+        assert!(matches!(
+            error,
+            BiError::ParseError(BiParserError::DecompressionError(_))
+        ));
+        assert!(error.to_string().contains("exceeds the limit"));
+
+        let mut reader = BiReader::new(&b":z data 3\nabc\n"[..]);
+        assert!(matches!(
+            reader.read_field_default().unwrap_err(),
+            BiError::ParseError(BiParserError::DecompressionError(_))
+        ));
+        let mut reader = BiReader::new(bytes.as_slice()).with_dialect(Dialect::Strict);
+        assert!(reader.read_field_default().is_err());
+    }
 }
@@ -0,0 +1,221 @@
+//! # Slice Reader Module
+//!
+//! Provides the `SliceReader`, a parser over an in-memory byte slice that does not depend on
+//! `std::io` and is available without the `std` feature.
+
+use super::decode::{
+    decode_blob, decode_blob_size, decode_gzip_blob, decode_marker, decode_name, decode_scalar,
+};
+use super::error::BiParserError;
+use crate::bi_core::error::BiError;
+use crate::bi_core::types::{BiField, Dialect, FieldMarker};
+use crate::bi_core::{DEFAULT_DECOMPRESSION_LIMIT, NEWLINE, SPACE};
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// A reader for parsing bi format data held in a byte slice.
+pub struct SliceReader<'a> {
+    input: &'a [u8],
+    position: usize,
+    dialect: Dialect,
+}
+
+impl<'a> SliceReader<'a> {
+    /// Constructor.
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            position: 0,
+            dialect: Dialect::default(),
+        }
+    }
+
+    /// Sets the dialect of field markers the reader accepts (defaults to [`Dialect::Signed`]).
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Byte offset of the next unread byte in the input.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Whether all of the input has been consumed.
+    pub fn is_empty(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    /// Read a field with validation enabled (default behavior).
+    ///
+    /// Equivalent to `read_field(true)`.
+    pub fn read_field_default(&mut self) -> Result<BiField, BiError> {
+        self.read_field(true)
+    }
+
+    /// Read a single field from the input. On error the position is left unchanged.
+    ///
+    /// ### Parameters
+    /// - `validate`: Whether or not to perform validation that the parsed data conforms to the bi
+    ///   format specification.
+    ///
+    /// ### Returns
+    /// `Result<BiField, BiError>`
+    pub fn read_field(&mut self, validate: bool) -> Result<BiField, BiError> {
+        let start = self.position;
+        let result = self.parse_field(validate);
+        if result.is_err() {
+            self.position = start;
+        }
+        result
+    }
+
+    fn parse_field(&mut self, validate: bool) -> Result<BiField, BiError> {
+        // Read the 3-byte marker consisting of `:`, the marker type, and a space.
+        let marker: [u8; 3] = self
+            .take(3)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| BiParserError::UnexpectedEof("while reading marker".to_string()))?;
+        let marker_type = decode_marker(marker, validate, self.dialect)?;
+
+        // Read the field name up to the next space.
+        let name_bytes = self.take_until(SPACE, "field name")?.to_vec();
+        decode_name(&name_bytes, validate)?;
+
+        match marker_type {
+            FieldMarker::Blob | FieldMarker::GzipBlob => {
+                let size_bytes = self.take_until(NEWLINE, "blob size")?;
+                let size = decode_blob_size(size_bytes)?;
+
+                let data: Vec<u8> = size
+                    .checked_add(1)
+                    .and_then(|len| self.take(len))
+                    .ok_or_else(|| {
+                        BiParserError::UnexpectedEof("while reading blob content".to_string())
+                    })?
+                    .to_vec();
+
+                let field = decode_blob(name_bytes, data, size, validate)?;
+                if marker_type == FieldMarker::GzipBlob {
+                    return Ok(decode_gzip_blob(field, DEFAULT_DECOMPRESSION_LIMIT)?);
+                }
+                Ok(field)
+            }
+            marker_type => {
+                let value_bytes = self.take_until(NEWLINE, "field value")?;
+                Ok(decode_scalar(
+                    &marker_type,
+                    name_bytes,
+                    value_bytes,
+                    validate,
+                )?)
+            }
+        }
+    }
+
+    /// Consumes exactly `len` bytes, or nothing if fewer remain.
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.position.checked_add(len)?;
+        let bytes = self.input.get(self.position..end)?;
+        self.position = end;
+        Some(bytes)
+    }
+
+    /// Consumes bytes up to and including the next `delimiter`, returning them without it.
+    fn take_until(&mut self, delimiter: u8, what: &str) -> Result<&'a [u8], BiParserError> {
+        let rest = &self.input[self.position..];
+        let len = rest
+            .iter()
+            .position(|b| *b == delimiter)
+            .ok_or_else(|| BiParserError::UnexpectedEof(format!("while reading {}", what)))?;
+        self.position += len + 1;
+        Ok(&rest[..len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::error::BiValidationError;
+
+    #[test]
+    fn test_read_fields() {
+        let input = b":i count 42\n:s delta -1\n:b data 5\nhello\n";
+        let mut reader = SliceReader::new(input);
+
+        assert_eq!(
+            reader.read_field_default().unwrap(),
+            BiField::Integer {
+                name: b"count".to_vec(),
+                value: 42
+            }
+        );
+        assert_eq!(
+            reader.read_field_default().unwrap(),
+            BiField::SignedInteger {
+                name: b"delta".to_vec(),
+                value: -1
+            }
+        );
+        assert_eq!(
+            reader.read_field_default().unwrap(),
+            BiField::Blob {
+                name: b"data".to_vec(),
+                data: b"hello".to_vec()
+            }
+        );
+        assert!(reader.is_empty());
+        assert_eq!(reader.position(), input.len());
+    }
+
+    #[test]
+    fn test_error_keeps_position() {
+        let mut reader = SliceReader::new(b":i count 42\n:b data 10\nhello\n");
+        reader.read_field_default().unwrap();
+        let position = reader.position();
+
+        assert!(matches!(
+            reader.read_field_default().unwrap_err(),
+            BiError::ParseError(BiParserError::UnexpectedEof(_))
+        ));
+        assert_eq!(reader.position(), position);
+    }
+
+    #[test]
+    fn test_invalid_fields() {
+        let mut reader = SliceReader::new(b":f ratio 0.5\n");
+        assert!(matches!(
+            reader.read_field_default().unwrap_err(),
+            BiError::ParseError(BiParserError::ValidationError(
+                BiValidationError::InvalidMarkerType('f')
+            ))
+        ));
+
+        let mut reader = SliceReader::new(b":i count 42");
+        assert!(matches!(
+            reader.read_field_default().unwrap_err(),
+            BiError::ParseError(BiParserError::UnexpectedEof(_))
+        ));
+
+        let mut reader = SliceReader::new(b":b data 3\nhello\n");
+        assert!(matches!(
+            reader.read_field_default().unwrap_err(),
+            BiError::ParseError(BiParserError::ValidationError(
+                BiValidationError::InvalidBlob(_)
+            ))
+        ));
+
+        // Not a gzip stream, and without the `compression` feature not decompressed at all.
+        let mut reader = SliceReader::new(b":z data 3\nabc\n");
+        let error = reader.read_field_default().unwrap_err();
+        assert!(matches!(
+            error,
+            BiError::ParseError(BiParserError::DecompressionError(_))
+        ));
+        #[cfg(not(feature = "compression"))]
+        assert!(error
+            .to_string()
+            .contains("`compression` feature is disabled"));
+    }
+}
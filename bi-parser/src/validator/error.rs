@@ -2,8 +2,9 @@
 //!
 //! This module provides the validator error type.
 
-use crate::bi_core::{BIDOCS, MARKER_BLOB, MARKER_INT, MARKER_SYM};
-use std::fmt;
+use crate::bi_core::{BIDOCS, MARKER_SYM};
+use alloc::string::String;
+use core::fmt;
 use thiserror::Error;
 
 /// Error that occurs during validation of bi format fields.
@@ -19,10 +20,14 @@ pub enum BiValidationError {
     InvalidFieldName(String),
     /// Integer value contains non-digit characters or is otherwise malformed.
     InvalidInteger(String),
+    /// Float value is not a valid IEEE-754 double literal.
+    InvalidFloat(String),
+    /// Boolean value is neither `true` nor `false`.
+    InvalidBool(String),
     /// Blob content does not match declared size or is missing trailing newline.
     InvalidBlob(String),
     /// String content contains invalid UTF-8 encoding.
-    Utf8Error(#[from] std::str::Utf8Error),
+    Utf8Error(#[from] core::str::Utf8Error),
 }
 
 impl fmt::Display for BiValidationError {
@@ -31,7 +36,7 @@ impl fmt::Display for BiValidationError {
             Self::InvalidMarkerSymbol(found) => write!(
                 f,
                 "Invalid marker symbol: expected `{s}`, found `{f}`",
-                s = MARKER_SYM,
+                s = MARKER_SYM as char,
                 f = found
             ),
             Self::InvalidMarkerFormat(msg) => write!(
@@ -42,14 +47,15 @@ impl fmt::Display for BiValidationError {
             ),
             Self::InvalidMarkerType(found) => write!(
                 f,
-                "Invalid field marker: expected `{s}{i}` or `{s}{b}`, found `{f}`",
-                s = MARKER_SYM,
-                i = MARKER_INT,
-                b = MARKER_BLOB,
-                f = found
+                "Invalid field marker: `{s}{f}` is not supported by the active dialect\nRefer to {d}",
+                s = MARKER_SYM as char,
+                f = found,
+                d = BIDOCS
             ),
             Self::InvalidFieldName(name) => write!(f, "Invalid field name: {}", name),
             Self::InvalidInteger(found) => write!(f, "Invalid integer: {}", found),
+            Self::InvalidFloat(found) => write!(f, "Invalid float: {}", found),
+            Self::InvalidBool(found) => write!(f, "Invalid boolean: {}", found),
             Self::InvalidBlob(msg) => write!(f, "Invalid blob: {}", msg),
             Self::Utf8Error(err) => write!(f, "UTF-8 decoding error: {}", err),
         }
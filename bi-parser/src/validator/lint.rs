@@ -0,0 +1,577 @@
+//! # Lint Module
+//!
+//! Provides `validate_stream`, which walks an entire bi file and collects every problem found
+//! instead of stopping at the first error like the readers do.
+
+use super::error::BiValidationError;
+use super::utils::{
+    validate_big_integer, validate_big_signed_integer, validate_bool, validate_field_name,
+    validate_float,
+};
+use crate::bi_core::types::{Dialect, FieldMarker};
+use crate::bi_core::{MARKER_SYM, NEWLINE, SPACE};
+use crate::parser::decode::decode_blob_size;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Data a reader accepts but that is likely unintended (e.g. trailing whitespace).
+    Warning,
+    /// Data a reader rejects or that breaks the configured rules.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Kind of problem a diagnostic reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// Field doesn't start with a well formed `:x ` marker or the marker type is unknown.
+    InvalidMarker,
+    /// Marker type is known but not part of the active dialect.
+    UnsupportedMarker,
+    /// Field name is empty, not UTF-8, or not followed by a value.
+    InvalidName,
+    /// Scalar value or blob size doesn't parse for its marker type.
+    InvalidValue,
+    /// Blob content doesn't match its declared size.
+    BlobSizeMismatch,
+    /// Last field isn't terminated by a newline.
+    MissingTrailingNewline,
+    /// Input ends in the middle of a field.
+    UnexpectedEof,
+    /// Bytes after the last field that don't form a field.
+    TrailingGarbage,
+    /// Field name repeated where the options forbid it.
+    DuplicateName,
+}
+
+/// A single problem found in a bi file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub severity: Severity,
+    /// Byte offset into the input the problem starts at.
+    pub offset: usize,
+    /// 1-based line of `offset`.
+    pub line: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Whether the diagnostic has `Severity::Error`.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, byte {}: {}",
+            self.severity, self.line, self.offset, self.message
+        )
+    }
+}
+
+/// Which field names may not appear more than once.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DuplicateNames {
+    /// Names may repeat (default, e.g. rere snapshots repeat a set of names per test).
+    #[default]
+    Allow,
+    /// Every name must be unique.
+    ForbidAll,
+    /// Only the listed names must be unique.
+    Forbid(Vec<Vec<u8>>),
+}
+
+impl DuplicateNames {
+    fn forbids(&self, name: &[u8]) -> bool {
+        match self {
+            Self::Allow => false,
+            Self::ForbidAll => true,
+            Self::Forbid(names) => names.iter().any(|n| n == name),
+        }
+    }
+}
+
+/// Options for [`validate_stream`].
+#[derive(Debug, Clone, Default)]
+pub struct LintOptions {
+    dialect: Dialect,
+    duplicate_names: DuplicateNames,
+}
+
+impl LintOptions {
+    /// Constructor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the dialect of field markers that are accepted (defaults to [`Dialect::Signed`]).
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Sets which field names may not repeat (defaults to [`DuplicateNames::Allow`]).
+    pub fn with_duplicate_names(mut self, duplicate_names: DuplicateNames) -> Self {
+        self.duplicate_names = duplicate_names;
+        self
+    }
+}
+
+/// Walks all of `input` and returns every problem found, in input order. An empty result means
+/// the input reads cleanly with validation enabled.
+///
+/// After an error that leaves the field boundary unknown (e.g. a malformed marker or a blob
+/// whose size doesn't match), linting resumes at the next line starting with `:`.
+///
+/// ### Parameters
+/// - `input`: Entire bi file contents.
+/// - `options`: Dialect and duplicate name rules to check against.
+pub fn validate_stream(input: &[u8], options: &LintOptions) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        input,
+        options,
+        diagnostics: Vec::new(),
+        seen: BTreeMap::new(),
+        fields: 0,
+        line_cursor: (0, 1),
+    };
+    let mut position = Some(0);
+    while let Some(start) = position.filter(|p| *p < input.len()) {
+        position = linter.lint_field(start);
+    }
+    linter.diagnostics
+}
+
+/// Reads `reader` to the end and lints it with [`validate_stream`].
+///
+/// ### Parameters
+/// - `reader`: Source of the entire bi file.
+/// - `options`: Dialect and duplicate name rules to check against.
+#[cfg(feature = "std")]
+pub fn validate_reader<R: std::io::Read>(
+    mut reader: R,
+    options: &LintOptions,
+) -> Result<Vec<Diagnostic>, crate::bi_core::error::BiError> {
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input)?;
+    Ok(validate_stream(&input, options))
+}
+
+struct Linter<'a> {
+    input: &'a [u8],
+    options: &'a LintOptions,
+    diagnostics: Vec<Diagnostic>,
+    /// Line of the first occurrence of each name.
+    seen: BTreeMap<&'a [u8], usize>,
+    /// Number of fields whose marker was well formed.
+    fields: usize,
+    /// Last `(offset, line)` a line number was computed for.
+    line_cursor: (usize, usize),
+}
+
+impl<'a> Linter<'a> {
+    /// Lints the field starting at `start`, returning where the next field starts or `None`
+    /// when the rest of the input can't be linted.
+    fn lint_field(&mut self, start: usize) -> Option<usize> {
+        let input = self.input;
+        let rest = &input[start..];
+
+        let marker_type = match rest {
+            [MARKER_SYM, byte, SPACE, ..] => FieldMarker::from_byte(*byte).ok_or(*byte),
+            _ => return self.lint_garbage(start),
+        };
+        self.fields += 1;
+        let marker_type = match marker_type {
+            Ok(marker_type) => marker_type,
+            Err(byte) => {
+                let message = format!("unknown marker type `:{}`", byte as char);
+                self.error(DiagnosticKind::InvalidMarker, start, message);
+                return self.resync(start);
+            }
+        };
+        if !self.options.dialect.supports(&marker_type) {
+            let message = format!(
+                "marker `:{}` is not supported by the active dialect",
+                marker_type.to_byte() as char
+            );
+            self.error(DiagnosticKind::UnsupportedMarker, start, message);
+        }
+
+        // Field name, up to the space before the value.
+        let name_start = start + 3;
+        let Some(name_end) = find(input, name_start, |b| b == SPACE || b == NEWLINE) else {
+            self.error(
+                DiagnosticKind::UnexpectedEof,
+                name_start,
+                "input ends while reading field name".to_string(),
+            );
+            return None;
+        };
+        if input[name_end] == NEWLINE {
+            self.error(
+                DiagnosticKind::InvalidName,
+                name_start,
+                "field name is not followed by a value".to_string(),
+            );
+            return Some(name_end + 1);
+        }
+        let name = &input[name_start..name_end];
+        if let Err(e) = validate_field_name(name) {
+            self.error(DiagnosticKind::InvalidName, name_start, e.to_string());
+        }
+        self.check_duplicate(name, name_start);
+
+        let value_start = name_end + 1;
+        match marker_type {
+            FieldMarker::Blob | FieldMarker::GzipBlob => self.lint_blob(value_start),
+            marker_type => self.lint_scalar(&marker_type, value_start),
+        }
+    }
+
+    fn lint_scalar(&mut self, marker_type: &FieldMarker, value_start: usize) -> Option<usize> {
+        let input = self.input;
+        let value_end = find(input, value_start, |b| b == NEWLINE);
+        let value = &input[value_start..value_end.unwrap_or(input.len())];
+        let result = match marker_type {
+            FieldMarker::Integer => validate_big_integer(value),
+            FieldMarker::SignedInteger => validate_big_signed_integer(value),
+            FieldMarker::Float => validate_float(value),
+            FieldMarker::Bool => validate_bool(value),
+            FieldMarker::Blob | FieldMarker::GzipBlob => {
+                unreachable!("blobs are linted by lint_blob")
+            }
+        };
+        if let Err(e) = result {
+            self.error(DiagnosticKind::InvalidValue, value_start, e.to_string());
+        }
+
+        match value_end {
+            Some(end) => Some(end + 1),
+            None => {
+                self.error(
+                    DiagnosticKind::MissingTrailingNewline,
+                    input.len(),
+                    "last field is missing its trailing newline".to_string(),
+                );
+                None
+            }
+        }
+    }
+
+    fn lint_blob(&mut self, size_start: usize) -> Option<usize> {
+        let input = self.input;
+        let Some(size_end) = find(input, size_start, |b| b == NEWLINE) else {
+            self.error(
+                DiagnosticKind::UnexpectedEof,
+                size_start,
+                "input ends while reading blob size".to_string(),
+            );
+            return None;
+        };
+        // Same parsing as the readers, so both agree on which sizes are valid.
+        let size = match decode_blob_size(&input[size_start..size_end]) {
+            Ok(size) => size,
+            Err(e) => {
+                self.error(DiagnosticKind::InvalidValue, size_start, e.to_string());
+                return self.resync(size_end);
+            }
+        };
+
+        let content_start = size_end + 1;
+        let remaining = input.len() - content_start;
+        if size > remaining {
+            self.error(
+                DiagnosticKind::BlobSizeMismatch,
+                content_start,
+                format!("blob declares {} bytes but only {} remain", size, remaining),
+            );
+            return None;
+        }
+        let content_end = content_start + size;
+        if content_end == input.len() {
+            self.error(
+                DiagnosticKind::MissingTrailingNewline,
+                content_end,
+                "last field is missing its trailing newline".to_string(),
+            );
+            return None;
+        }
+        if input[content_end] == NEWLINE {
+            return Some(content_end + 1);
+        }
+
+        // The content doesn't end where declared, guess the real size from the next field.
+        let next = self.resync(content_start);
+        let actual_end = match next {
+            Some(next) => next - 1,
+            None if input.ends_with(&[NEWLINE]) => input.len() - 1,
+            None => input.len(),
+        };
+        self.error(
+            DiagnosticKind::BlobSizeMismatch,
+            content_start,
+            format!(
+                "blob declares {} bytes but its content appears to be {} bytes",
+                size,
+                actual_end - content_start
+            ),
+        );
+        next
+    }
+
+    /// Reports bytes at `start` that don't form a field marker.
+    fn lint_garbage(&mut self, start: usize) -> Option<usize> {
+        let next = self.resync(start);
+        let rest = &self.input[start..];
+        if next.is_none() && self.fields > 0 {
+            let (severity, message) = if rest.iter().all(u8::is_ascii_whitespace) {
+                (
+                    Severity::Warning,
+                    "trailing whitespace after the last field",
+                )
+            } else {
+                (Severity::Error, "trailing data after the last field")
+            };
+            self.push(
+                DiagnosticKind::TrailingGarbage,
+                severity,
+                start,
+                format!("{} ({} bytes)", message, rest.len()),
+            );
+            return None;
+        }
+
+        let message = match rest.first() {
+            Some(&byte) if byte != MARKER_SYM => {
+                BiValidationError::InvalidMarkerSymbol(byte as char).to_string()
+            }
+            _ => "invalid marker format: expected single space after marker".to_string(),
+        };
+        self.error(DiagnosticKind::InvalidMarker, start, message);
+        next
+    }
+
+    fn check_duplicate(&mut self, name: &'a [u8], offset: usize) {
+        if !self.options.duplicate_names.forbids(name) {
+            return;
+        }
+        match self.seen.get(name) {
+            Some(&first_line) => {
+                let message = format!(
+                    "duplicate field name `{}`, first used on line {}",
+                    String::from_utf8_lossy(name),
+                    first_line
+                );
+                self.error(DiagnosticKind::DuplicateName, offset, message);
+            }
+            None => {
+                let line = self.line_of(offset);
+                self.seen.insert(name, line);
+            }
+        }
+    }
+
+    /// Offset of the next line after `from` that starts with the marker symbol.
+    fn resync(&self, from: usize) -> Option<usize> {
+        let input = self.input;
+        (from..input.len().saturating_sub(1))
+            .find(|&i| input[i] == NEWLINE && input[i + 1] == MARKER_SYM)
+            .map(|i| i + 1)
+    }
+
+    fn error(&mut self, kind: DiagnosticKind, offset: usize, message: String) {
+        self.push(kind, Severity::Error, offset, message);
+    }
+
+    fn push(&mut self, kind: DiagnosticKind, severity: Severity, offset: usize, message: String) {
+        let line = self.line_of(offset);
+        self.diagnostics.push(Diagnostic {
+            kind,
+            severity,
+            offset,
+            line,
+            message,
+        });
+    }
+
+    /// 1-based line number of `offset`. Counts on from the last lookup, so linting in input order
+    /// scans the input once. An earlier offset is counted back from the cursor, which stays put.
+    fn line_of(&mut self, offset: usize) -> usize {
+        let (cursor, line) = self.line_cursor;
+        let newlines = |range: &[u8]| range.iter().filter(|b| **b == NEWLINE).count();
+        if offset < cursor {
+            return line - newlines(&self.input[offset..cursor]);
+        }
+        let line = line + newlines(&self.input[cursor..offset]);
+        self.line_cursor = (offset, line);
+        line
+    }
+}
+
+/// Index of the first byte at or after `from` matching `predicate`.
+fn find(input: &[u8], from: usize, predicate: impl Fn(u8) -> bool) -> Option<usize> {
+    input[from..]
+        .iter()
+        .position(|b| predicate(*b))
+        .map(|i| from + i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::slice::SliceReader;
+    use alloc::vec;
+
+    fn kinds(input: &[u8], options: &LintOptions) -> Vec<(DiagnosticKind, Severity, usize)> {
+        validate_stream(input, options)
+            .into_iter()
+            .map(|d| (d.kind, d.severity, d.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_input() {
+        let input = b":i count 2\n:b shell 4\necho\n:s returncode -1\n:b shell 2\nls\n";
+        assert!(validate_stream(input, &LintOptions::new()).is_empty());
+        assert!(validate_stream(b"", &LintOptions::new()).is_empty());
+    }
+
+    #[test]
+    fn test_collects_every_problem() {
+        let input = b":i count x\n:q what 1\n:b data 10\nhi\n:f ratio 0.5\n:i last 1\n  \n";
+        assert_eq!(
+            kinds(input, &LintOptions::new()),
+            vec![
+                (DiagnosticKind::InvalidValue, Severity::Error, 1),
+                (DiagnosticKind::InvalidMarker, Severity::Error, 2),
+                (DiagnosticKind::BlobSizeMismatch, Severity::Error, 4),
+                (DiagnosticKind::UnsupportedMarker, Severity::Error, 5),
+                (DiagnosticKind::TrailingGarbage, Severity::Warning, 7),
+            ]
+        );
+
+        let diagnostics = validate_stream(input, &LintOptions::new());
+        assert_eq!(diagnostics[2].offset, 32);
+        assert!(diagnostics[2].message.contains("appears to be 2 bytes"));
+        assert_eq!(
+            diagnostics[2].to_string(),
+            "error at line 4, byte 32: blob declares 10 bytes but its content appears to be 2 bytes"
+        );
+    }
+
+    #[test]
+    fn test_dialect() {
+        let input = b":f ratio 0.5\n:t flag maybe\n";
+        let options = LintOptions::new().with_dialect(Dialect::Extended);
+        assert_eq!(
+            kinds(input, &options),
+            vec![(DiagnosticKind::InvalidValue, Severity::Error, 2)]
+        );
+    }
+
+    #[test]
+    fn test_double_sign() {
+        assert_eq!(
+            kinds(b":s a -+1\n:s b +-1\n:s c -1\n", &LintOptions::new()),
+            vec![
+                (DiagnosticKind::InvalidValue, Severity::Error, 1),
+                (DiagnosticKind::InvalidValue, Severity::Error, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blob_size() {
+        // Sizes are parsed like the readers parse them.
+        let input = b":b data +2\nhi\n";
+        assert!(validate_stream(input, &LintOptions::new()).is_empty());
+        assert!(SliceReader::new(input).read_field_default().is_ok());
+
+        for input in [
+            &b":b data -2\nhi\n"[..],
+            b":b data ++2\nhi\n",
+            b":b data \n\n",
+        ] {
+            assert_eq!(
+                kinds(input, &LintOptions::new()),
+                vec![(DiagnosticKind::InvalidValue, Severity::Error, 1)]
+            );
+            assert!(SliceReader::new(input).read_field_default().is_err());
+        }
+    }
+
+    #[test]
+    fn test_end_of_input() {
+        assert_eq!(
+            kinds(b":i count 1", &LintOptions::new()),
+            vec![(DiagnosticKind::MissingTrailingNewline, Severity::Error, 1)]
+        );
+        assert_eq!(
+            kinds(b":b data 2\nhi", &LintOptions::new()),
+            vec![(DiagnosticKind::MissingTrailingNewline, Severity::Error, 2)]
+        );
+        assert_eq!(
+            kinds(b":b data 20\nhi\n", &LintOptions::new()),
+            vec![(DiagnosticKind::BlobSizeMismatch, Severity::Error, 2)]
+        );
+        assert_eq!(
+            kinds(b":i count", &LintOptions::new()),
+            vec![(DiagnosticKind::UnexpectedEof, Severity::Error, 1)]
+        );
+        assert_eq!(
+            kinds(b":i count 1\ngarbage", &LintOptions::new()),
+            vec![(DiagnosticKind::TrailingGarbage, Severity::Error, 2)]
+        );
+        assert_eq!(
+            kinds(b"garbage\n:i count 1\n", &LintOptions::new()),
+            vec![(DiagnosticKind::InvalidMarker, Severity::Error, 1)]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let input = b":i count 1\n:b data 1\na\n:i count 2\n:b data 1\nb\n";
+        assert!(validate_stream(input, &LintOptions::new()).is_empty());
+
+        let options = LintOptions::new().with_duplicate_names(DuplicateNames::ForbidAll);
+        let diagnostics = validate_stream(input, &options);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.kind == DiagnosticKind::DuplicateName));
+        assert_eq!(
+            diagnostics[0].message,
+            "duplicate field name `count`, first used on line 1"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "duplicate field name `data`, first used on line 2"
+        );
+        assert_eq!(diagnostics[1].line, 5);
+
+        let options =
+            LintOptions::new().with_duplicate_names(DuplicateNames::Forbid(vec![b"data".to_vec()]));
+        assert_eq!(
+            kinds(input, &options),
+            vec![(DiagnosticKind::DuplicateName, Severity::Error, 5)]
+        );
+    }
+}
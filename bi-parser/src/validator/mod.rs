@@ -1,6 +1,8 @@
 //! # Validator Module
 //!
-//! This module provides utilities for validating bi format fields according to the specification.
+//! This module provides utilities for validating bi format fields according to the specification,
+//! and for linting whole files.
 
 pub mod error;
+pub mod lint;
 pub mod utils;
@@ -5,6 +5,9 @@
 use super::error::BiValidationError;
 use crate::bi_core::types::FieldMarker;
 use crate::bi_core::{MARKER_SYM, NEWLINE, SPACE};
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
 
 /// Validates a bi format field marker. Checks:
 /// - First byte is the correct symbol
@@ -14,7 +17,7 @@ use crate::bi_core::{MARKER_SYM, NEWLINE, SPACE};
 /// ### Parameters
 /// - `marker`: 3-byte array containing the marker bytes.
 /// - `full_validation`: Whether to perform full validation, prevents duplicate operations when
-/// also parsing the field.
+///   also parsing the field.
 pub fn validate_marker(marker: [u8; 3], full_validation: bool) -> Result<(), BiValidationError> {
     // Check marker symbol.
     if marker[0] != MARKER_SYM {
@@ -44,14 +47,13 @@ pub fn validate_marker(marker: [u8; 3], full_validation: bool) -> Result<(), BiV
 ///
 /// ### Parameters
 /// - `name_bytes`: Bytes containing the field names.
-/// also parsing the field.
 pub fn validate_field_name(name_bytes: &[u8]) -> Result<(), BiValidationError> {
     if name_bytes.is_empty() {
         return Err(BiValidationError::InvalidFieldName(
             "empty field name".to_owned(),
         ));
     }
-    std::str::from_utf8(name_bytes)?;
+    core::str::from_utf8(name_bytes)?;
 
     Ok(())
 }
@@ -67,7 +69,7 @@ pub fn validate_integer(bytes: &[u8]) -> Result<(), BiValidationError> {
         ));
     }
 
-    let s = std::str::from_utf8(bytes)?;
+    let s = core::str::from_utf8(bytes)?;
     if s.parse::<u64>().is_err() {
         return Err(BiValidationError::InvalidInteger(s.to_owned()));
     }
@@ -87,7 +89,7 @@ pub fn validate_signed_integer(bytes: &[u8]) -> Result<(), BiValidationError> {
         ));
     }
 
-    let s = std::str::from_utf8(bytes)?;
+    let s = core::str::from_utf8(bytes)?;
     if s.parse::<i64>().is_err() {
         return Err(BiValidationError::InvalidInteger(s.to_owned()));
     }
@@ -95,6 +97,72 @@ pub fn validate_signed_integer(bytes: &[u8]) -> Result<(), BiValidationError> {
     Ok(())
 }
 
+/// Validates a byte sequence contains only ASCII digits and is not empty. Unlike
+/// [`validate_integer`] there is no upper bound on the value.
+///
+/// ### Parameters
+/// - `bytes`: Bytes to validate.
+pub fn validate_big_integer(bytes: &[u8]) -> Result<(), BiValidationError> {
+    let digits = bytes.strip_prefix(b"+").unwrap_or(bytes);
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return Err(BiValidationError::InvalidInteger(
+            String::from_utf8_lossy(bytes).into_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validates a byte sequence contains only ASCII digits, optionally preceeded by a single sign,
+/// and is not empty. Unlike [`validate_signed_integer`] there is no bound on the value.
+///
+/// ### Parameters
+/// - `bytes`: Bytes to validate.
+pub fn validate_big_signed_integer(bytes: &[u8]) -> Result<(), BiValidationError> {
+    let digits = match bytes.first() {
+        Some(b'-' | b'+') => &bytes[1..],
+        _ => bytes,
+    };
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return Err(BiValidationError::InvalidInteger(
+            String::from_utf8_lossy(bytes).into_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validates a byte sequence is a valid IEEE-754 double literal (e.g. `1.5`, `-0.0`, `1e300`,
+/// `inf`, `NaN`) and is not empty.
+///
+/// ### Parameters
+/// - `bytes`: Bytes to validate.
+pub fn validate_float(bytes: &[u8]) -> Result<(), BiValidationError> {
+    if bytes.is_empty() {
+        return Err(BiValidationError::InvalidFloat("empty float".to_owned()));
+    }
+
+    let s = core::str::from_utf8(bytes)?;
+    if s.parse::<f64>().is_err() {
+        return Err(BiValidationError::InvalidFloat(s.to_owned()));
+    }
+
+    Ok(())
+}
+
+/// Validates a byte sequence is exactly `true` or `false`.
+///
+/// ### Parameters
+/// - `bytes`: Bytes to validate.
+pub fn validate_bool(bytes: &[u8]) -> Result<(), BiValidationError> {
+    match bytes {
+        b"true" | b"false" => Ok(()),
+        _ => Err(BiValidationError::InvalidBool(
+            String::from_utf8_lossy(bytes).into_owned(),
+        )),
+    }
+}
+
 /// Validates blob content size and format. Checks:
 /// - Blob content includes trailing newline.
 /// - Content size matches expected size without trailing newline.
@@ -207,6 +275,76 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_validate_big_integer() {
+        // Valid integers, including values larger than `u64::MAX`
+        assert!(validate_big_integer(b"0").is_ok());
+        assert!(validate_big_integer(b"340282366920938463463374607431768211456").is_ok());
+
+        // Invalid integers
+        assert!(matches!(
+            validate_big_integer(b""),
+            Err(BiValidationError::InvalidInteger(_))
+        ));
+        assert!(matches!(
+            validate_big_integer(b"-1"),
+            Err(BiValidationError::InvalidInteger(_))
+        ));
+        assert!(matches!(
+            validate_big_integer(b"12a3"),
+            Err(BiValidationError::InvalidInteger(_))
+        ));
+
+        // Signed
+        assert!(validate_big_signed_integer(b"-99999999999999999999999").is_ok());
+        assert!(validate_big_signed_integer(b"+1").is_ok());
+        for double_sign in [&b"--1"[..], b"-+1", b"+-1", b"++1"] {
+            assert!(matches!(
+                validate_big_signed_integer(double_sign),
+                Err(BiValidationError::InvalidInteger(_))
+            ));
+        }
+        assert!(validate_big_integer(b"+-1").is_err());
+        assert!(matches!(
+            validate_big_signed_integer(b"-"),
+            Err(BiValidationError::InvalidInteger(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_float() {
+        // Valid floats
+        assert!(validate_float(b"1.5").is_ok());
+        assert!(validate_float(b"-0.0").is_ok());
+        assert!(validate_float(b"1e300").is_ok());
+        assert!(validate_float(b"inf").is_ok());
+        assert!(validate_float(b"NaN").is_ok());
+
+        // Invalid floats
+        assert!(matches!(
+            validate_float(b""),
+            Err(BiValidationError::InvalidFloat(_))
+        ));
+        assert!(matches!(
+            validate_float(b"1.5x"),
+            Err(BiValidationError::InvalidFloat(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_bool() {
+        assert!(validate_bool(b"true").is_ok());
+        assert!(validate_bool(b"false").is_ok());
+        assert!(matches!(
+            validate_bool(b"1"),
+            Err(BiValidationError::InvalidBool(_))
+        ));
+        assert!(matches!(
+            validate_bool(b"True"),
+            Err(BiValidationError::InvalidBool(_))
+        ));
+    }
+
     #[test]
     fn test_validate_blob() {
         // Valid blob
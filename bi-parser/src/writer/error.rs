@@ -3,6 +3,7 @@
 //! This module provides the writer error type.
 
 use crate::validator::error::BiValidationError;
+use alloc::string::String;
 use thiserror::Error;
 
 /// Error that occurs during writing of bi format data.
@@ -12,6 +13,11 @@ pub enum BiWriterError {
     #[error("Error writing onput: {0}")]
     WriteError(String),
 
+    /// Blob content could not be compressed.
+    #[cfg(feature = "compression")]
+    #[error("Error compressing blob: {0}")]
+    CompressionError(String),
+
     /// Field validation failed.
     #[error(transparent)]
     ValidationError(#[from] BiValidationError),
@@ -3,4 +3,7 @@
 //! This module provides functionaility for writing data to files in bi format.
 
 pub mod error;
+pub mod serialize;
+#[cfg(feature = "std")]
+#[allow(clippy::module_inception)]
 pub mod writer;
@@ -0,0 +1,241 @@
+//! # Serialize Module
+//!
+//! Provides serialization of fields into an in-memory buffer. Does not depend on `std::io` and is
+//! available without the `std` feature.
+
+use super::error::BiWriterError;
+use crate::bi_core::types::{BiField, Dialect, FieldMarker};
+use crate::bi_core::{MARKER_SYM, NEWLINE, SPACE};
+use crate::validator::error::BiValidationError;
+use crate::validator::utils::{validate_big_integer, validate_field_name};
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+/// Serialize a field with validation enabled using the default dialect.
+///
+/// Equivalent to `serialize_field(field, true, Dialect::default(), out)`.
+pub fn serialize_field_default(field: &BiField, out: &mut Vec<u8>) -> Result<(), BiWriterError> {
+    serialize_field(field, true, Dialect::default(), out)
+}
+
+/// Serialize a single field, appending it to `out`. Nothing is appended on error.
+///
+/// ### Parameters
+/// - `field`: The field to serialize.
+/// - `validate`: Whether or not to validate the field name and value.
+/// - `dialect`: The dialect of field markers that may be emitted.
+/// - `out`: Buffer to append the serialized field to.
+pub fn serialize_field(
+    field: &BiField,
+    validate: bool,
+    dialect: Dialect,
+    out: &mut Vec<u8>,
+) -> Result<(), BiWriterError> {
+    encode_field(field, validate, dialect, |bytes| {
+        out.extend_from_slice(bytes);
+        Ok(())
+    })
+}
+
+/// Validates a field and passes its serialized form to `emit` piece by piece. All validation
+/// happens before the first call to `emit`, so nothing is emitted for an invalid field.
+///
+/// ### Parameters
+/// - `field`: The field to serialize.
+/// - `validate`: Whether or not to validate the field name and value.
+/// - `dialect`: The dialect of field markers that may be emitted.
+/// - `emit`: Sink receiving consecutive pieces of the serialized field.
+pub(crate) fn encode_field<F>(
+    field: &BiField,
+    validate: bool,
+    dialect: Dialect,
+    emit: F,
+) -> Result<(), BiWriterError>
+where
+    F: FnMut(&[u8]) -> Result<(), BiWriterError>,
+{
+    encode_field_as(field, field.marker(), validate, dialect, emit)
+}
+
+/// Same as [`encode_field`], with the marker written for the field overridden by `marker`. Used
+/// to write a [`BiField::Blob`] holding compressed content as a `:z` field.
+pub(crate) fn encode_field_as<F>(
+    field: &BiField,
+    marker: FieldMarker,
+    validate: bool,
+    dialect: Dialect,
+    mut emit: F,
+) -> Result<(), BiWriterError>
+where
+    F: FnMut(&[u8]) -> Result<(), BiWriterError>,
+{
+    debug_assert!(marker == field.marker() || matches!(field, BiField::Blob { .. }));
+    if !dialect.supports(&marker) {
+        return Err(BiWriterError::ValidationError(
+            BiValidationError::InvalidMarkerType(marker.to_byte() as char),
+        ));
+    }
+    if validate {
+        validate_field_name(field.name())?;
+        if let BiField::BigInteger { value, .. } = field {
+            validate_big_integer(value.as_bytes())?;
+        }
+    }
+
+    emit(&[MARKER_SYM, marker.to_byte(), SPACE])?;
+    emit(field.name())?;
+    emit(&[SPACE])?;
+
+    let mut digits = [0u8; 40];
+    match field {
+        BiField::Integer { value, .. } => emit(format_u64(*value, &mut digits))?,
+        BiField::SignedInteger { value, .. } => {
+            if *value < 0 {
+                emit(b"-")?;
+            }
+            emit(format_u64(value.unsigned_abs(), &mut digits))?;
+        }
+        BiField::BigInteger { value, .. } | BiField::BigSignedInteger { value, .. } => {
+            emit(value.as_bytes())?
+        }
+        BiField::Float { value, .. } => {
+            // `Debug` produces the shortest representation that round-trips exactly.
+            let mut text = StackText::new(&mut digits);
+            write!(text, "{:?}", value).expect("float text fits in 40 bytes");
+            emit(text.as_bytes())?;
+        }
+        BiField::Bool { value, .. } => emit(if *value { b"true" } else { b"false" })?,
+        BiField::Blob { data, .. } => {
+            emit(format_u64(data.len() as u64, &mut digits))?;
+            emit(&[NEWLINE])?;
+            emit(data)?;
+        }
+    }
+    emit(&[NEWLINE])
+}
+
+/// Formats `value` as decimal digits at the end of `buf`, returning the digits.
+fn format_u64(mut value: u64, buf: &mut [u8; 40]) -> &[u8] {
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    &buf[start..]
+}
+
+/// Fixed capacity text buffer used to format floats without allocating.
+struct StackText<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> StackText<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl fmt::Write for StackText<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_fields() {
+        let mut out = Vec::new();
+        serialize_field_default(
+            &BiField::Integer {
+                name: b"count".to_vec(),
+                value: 42,
+            },
+            &mut out,
+        )
+        .unwrap();
+        serialize_field_default(
+            &BiField::Blob {
+                name: b"data".to_vec(),
+                data: b"hello".to_vec(),
+            },
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out, b":i count 42\n:b data 5\nhello\n");
+    }
+
+    #[test]
+    fn test_serialize_numbers() {
+        let mut out = Vec::new();
+        for value in [0, 7, u64::MAX] {
+            serialize_field_default(
+                &BiField::Integer {
+                    name: b"n".to_vec(),
+                    value,
+                },
+                &mut out,
+            )
+            .unwrap();
+        }
+        for value in [i64::MIN, -1, 0, i64::MAX] {
+            serialize_field_default(
+                &BiField::SignedInteger {
+                    name: b"n".to_vec(),
+                    value,
+                },
+                &mut out,
+            )
+            .unwrap();
+        }
+        serialize_field(
+            &BiField::Float {
+                name: b"f".to_vec(),
+                value: -2.2250738585072014e-308,
+            },
+            true,
+            Dialect::Extended,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            b":i n 0\n:i n 7\n:i n 18446744073709551615\n\
+              :s n -9223372036854775808\n:s n -1\n:s n 0\n:s n 9223372036854775807\n\
+              :f f -2.2250738585072014e-308\n"
+        );
+    }
+
+    #[test]
+    fn test_serialize_error_appends_nothing() {
+        let mut out = b":i count 42\n".to_vec();
+        let field = BiField::Float {
+            name: b"ratio".to_vec(),
+            value: 0.5,
+        };
+        assert!(matches!(
+            serialize_field_default(&field, &mut out),
+            Err(BiWriterError::ValidationError(
+                BiValidationError::InvalidMarkerType('f')
+            ))
+        ));
+        assert_eq!(out, b":i count 42\n");
+    }
+}
@@ -3,17 +3,59 @@
 //! Provides the `BiWriter`.
 
 use super::error::BiWriterError;
+use super::serialize::encode_field_as;
+use crate::bi_core::checksum::{Crc32, CHECKSUM_NAME};
+use crate::bi_core::types::{Dialect, FieldMarker};
 use crate::prelude::*;
-use crate::validator::utils::validate_field_name;
-use std::io::Write;
+use std::io::{BufWriter, Write};
 
-pub struct BiWriter<W> {
-    writer: W,
+/// A buffered writer for writing bi format files.
+///
+/// Output is buffered, so [`BiWriter::finish`] (or [`BiWriter::flush`] / [`BiWriter::into_inner`])
+/// must be called once the fields are written. Dropping the writer still flushes it, but any
+/// write error is then discarded and the checksum field (if enabled) is never written.
+pub struct BiWriter<W: Write> {
+    writer: BufWriter<W>,
+    dialect: Dialect,
+    /// Digest of the bytes written since the start or the last checksum field, when enabled.
+    crc: Option<Crc32>,
+    /// Minimum blob size to compress, when enabled.
+    #[cfg(feature = "compression")]
+    compress_threshold: Option<usize>,
 }
 
 impl<W: Write> BiWriter<W> {
     pub fn new(writer: W) -> Self {
-        Self { writer }
+        Self {
+            writer: BufWriter::new(writer),
+            dialect: Dialect::default(),
+            crc: None,
+            #[cfg(feature = "compression")]
+            compress_threshold: None,
+        }
+    }
+
+    /// Sets the dialect of field markers the writer may emit (defaults to [`Dialect::Signed`]).
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Enables the trailing checksum field (disabled by default). The checksum is written by
+    /// [`BiWriter::finish`] or [`BiWriter::write_checksum`], see [`crate::bi_core::checksum`] for
+    /// the convention.
+    pub fn with_checksum(mut self, checksum: bool) -> Self {
+        self.crc = checksum.then(Crc32::new);
+        self
+    }
+
+    /// Enables gzip compression of blobs of at least `threshold` bytes (disabled by default).
+    /// Compressed blobs are written as `:z` fields, see [`crate::bi_core::compression`], which
+    /// [`Dialect::Strict`] doesn't allow. Blobs that don't shrink are written as is.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, threshold: usize) -> Self {
+        self.compress_threshold = Some(threshold);
+        self
     }
 
     pub fn write_field_default(&mut self, field: &BiField) -> Result<(), BiError> {
@@ -21,62 +63,102 @@ impl<W: Write> BiWriter<W> {
     }
 
     pub fn write_field(&mut self, field: &BiField, validate: bool) -> Result<(), BiError> {
-        let mut content = Vec::new();
-
-        match field {
-            BiField::Integer { name, value } => {
-                if validate {
-                    validate_field_name(name).map_err(|e| BiWriterError::ValidationError(e))?;
-                }
-
-                content.extend_from_slice(&[MARKER_SYM, MARKER_INT, SPACE]);
-                content.extend_from_slice(name);
-                content.extend_from_slice(&[SPACE]);
-                content.extend_from_slice(value.to_string().as_bytes());
-                content.push(NEWLINE);
-            }
-            BiField::SignedInteger { name, value } => {
-                if validate {
-                    validate_field_name(name).map_err(|e| BiWriterError::ValidationError(e))?;
-                }
-
-                content.extend_from_slice(&[MARKER_SYM, MARKER_SINT, SPACE]);
-                content.extend_from_slice(name);
-                content.extend_from_slice(&[SPACE]);
-                content.extend_from_slice(value.to_string().as_bytes());
-                content.push(NEWLINE);
-            }
-            BiField::Blob { name, data } => {
-                if validate {
-                    validate_field_name(name).map_err(|e| BiWriterError::ValidationError(e))?;
-                }
-
-                content.extend_from_slice(&[MARKER_SYM, MARKER_BLOB, SPACE]);
-                content.extend_from_slice(name);
-                content.extend_from_slice(&[SPACE]);
-                content.extend_from_slice(data.len().to_string().as_bytes());
-                content.push(NEWLINE);
-                content.extend_from_slice(data);
-                content.push(NEWLINE);
-            }
+        #[cfg(feature = "compression")]
+        if let Some(compressed) = self.compress(field)? {
+            return self.encode(&compressed, FieldMarker::GzipBlob, validate);
         }
+        self.encode(field, field.marker(), validate)
+    }
+
+    /// Blob holding the compressed content of `field` if it's a blob that should be compressed.
+    #[cfg(feature = "compression")]
+    fn compress(&self, field: &BiField) -> Result<Option<BiField>, BiError> {
+        use crate::bi_core::compression::compress;
+
+        let (Some(threshold), BiField::Blob { name, data }) = (self.compress_threshold, field)
+        else {
+            return Ok(None);
+        };
+        if data.len() < threshold {
+            return Ok(None);
+        }
+        let compressed =
+            compress(data).map_err(|e| BiWriterError::CompressionError(e.to_string()))?;
+        if compressed.len() >= data.len() {
+            return Ok(None);
+        }
+        Ok(Some(BiField::Blob {
+            name: name.clone(),
+            data: compressed,
+        }))
+    }
+
+    fn encode(
+        &mut self,
+        field: &BiField,
+        marker: FieldMarker,
+        validate: bool,
+    ) -> Result<(), BiError> {
+        let writer = &mut self.writer;
+        let crc = &mut self.crc;
+        encode_field_as(field, marker, validate, self.dialect, |bytes| {
+            if let Some(crc) = crc.as_mut() {
+                crc.update(bytes);
+            }
+            writer
+                .write_all(bytes)
+                .map_err(|e| BiWriterError::WriteError(e.to_string()))
+        })?;
+        Ok(())
+    }
+
+    /// Writes a checksum field covering the fields written since the start or the last checksum
+    /// field. Does nothing unless checksums are enabled.
+    pub fn write_checksum(&mut self) -> Result<(), BiError> {
+        let Some(crc) = self.crc.take() else {
+            return Ok(());
+        };
+        let field = BiField::Integer {
+            name: CHECKSUM_NAME.to_vec(),
+            value: u64::from(crc.value()),
+        };
+        let result = self.write_field(&field, false);
+        self.crc = Some(Crc32::new());
+        result
+    }
+
+    /// Writes the checksum field (if enabled), flushes, and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, BiError> {
+        self.write_checksum()?;
+        self.into_inner()
+    }
 
+    /// Flushes buffered fields to the underlying writer.
+    pub fn flush(&mut self) -> Result<(), BiError> {
         self.writer
-            .write_all(&content)
+            .flush()
             .map_err(|e| BiWriterError::WriteError(e.to_string()))?;
         Ok(())
     }
+
+    /// Flushes buffered fields and returns the underlying writer.
+    pub fn into_inner(self) -> Result<W, BiError> {
+        Ok(self
+            .writer
+            .into_inner()
+            .map_err(|e| BiWriterError::WriteError(e.error().to_string()))?)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::validator::error::BiValidationError;
 
     fn write_and_verify(field: &BiField, expected: &[u8]) {
-        let mut buf = Vec::new();
-        let mut writer = BiWriter::new(&mut buf);
+        let mut writer = BiWriter::new(Vec::new());
         writer.write_field_default(field).unwrap();
-        assert_eq!(buf, expected);
+        assert_eq!(writer.into_inner().unwrap(), expected);
     }
 
     #[test]
@@ -115,6 +197,134 @@ mod tests {
         write_and_verify(&field, b":b empty 0\n\n");
     }
 
+    #[test]
+    fn test_write_big_integers() {
+        let field = BiField::BigInteger {
+            name: b"hash".to_vec(),
+            value: BigInt::from(u128::MAX),
+        };
+        write_and_verify(&field, b":i hash 340282366920938463463374607431768211455\n");
+
+        let field = BiField::BigSignedInteger {
+            name: b"low".to_vec(),
+            value: BigInt::parse(b"-99999999999999999999999").unwrap(),
+        };
+        write_and_verify(&field, b":s low -99999999999999999999999\n");
+
+        // Negative values can't be written with the unsigned marker.
+        let mut buf = Vec::new();
+        let mut writer = BiWriter::new(&mut buf);
+        let field = BiField::BigInteger {
+            name: b"hash".to_vec(),
+            value: BigInt::from(-1i64),
+        };
+        assert!(matches!(
+            writer.write_field_default(&field).unwrap_err(),
+            BiError::WriteError(BiWriterError::ValidationError(
+                BiValidationError::InvalidInteger(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_write_float_and_bool() {
+        let mut writer = BiWriter::new(Vec::new()).with_dialect(Dialect::Extended);
+        for value in [0.1, -0.0, 1e300, f64::INFINITY] {
+            writer
+                .write_field_default(&BiField::Float {
+                    name: b"ratio".to_vec(),
+                    value,
+                })
+                .unwrap();
+        }
+        writer
+            .write_field_default(&BiField::Bool {
+                name: b"ok".to_vec(),
+                value: true,
+            })
+            .unwrap();
+        assert_eq!(
+            writer.into_inner().unwrap(),
+            b":f ratio 0.1\n:f ratio -0.0\n:f ratio 1e300\n:f ratio inf\n:t ok true\n"
+        );
+    }
+
+    #[test]
+    fn test_float_round_trip() {
+        let values = [0.1, 1.0 / 3.0, -2.5e-310, f64::MAX, f64::MIN_POSITIVE];
+        let mut writer = BiWriter::new(Vec::new()).with_dialect(Dialect::Extended);
+        for value in values {
+            writer
+                .write_field_default(&BiField::Float {
+                    name: b"x".to_vec(),
+                    value,
+                })
+                .unwrap();
+        }
+
+        let buf = writer.into_inner().unwrap();
+        let mut reader = BiReader::new(buf.as_slice()).with_dialect(Dialect::Extended);
+        for value in values {
+            match reader.read_field_default().unwrap() {
+                BiField::Float { value: read, .. } => assert_eq!(read.to_bits(), value.to_bits()),
+                _ => panic!("Expected float field"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_extended_markers_require_dialect() {
+        let mut buf = Vec::new();
+        let mut writer = BiWriter::new(&mut buf);
+        let field = BiField::Bool {
+            name: b"ok".to_vec(),
+            value: true,
+        };
+        assert!(matches!(
+            writer.write_field_default(&field).unwrap_err(),
+            BiError::WriteError(BiWriterError::ValidationError(
+                BiValidationError::InvalidMarkerType('t')
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_flush() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = BiWriter::new(&mut buf);
+            writer
+                .write_field_default(&BiField::Integer {
+                    name: b"count".to_vec(),
+                    value: 42,
+                })
+                .unwrap();
+            writer.flush().unwrap();
+        }
+        assert_eq!(buf, b":i count 42\n");
+    }
+
+    #[test]
+    fn test_checksum() {
+        let field = BiField::Integer {
+            name: b"count".to_vec(),
+            value: 42,
+        };
+        let mut writer = BiWriter::new(Vec::new()).with_checksum(true);
+        writer.write_field_default(&field).unwrap();
+        let buf = writer.finish().unwrap();
+
+        let mut crc = Crc32::new();
+        crc.update(b":i count 42\n");
+        let expected = format!(":i count 42\n:i bi.crc32 {}\n", crc.value());
+        assert_eq!(buf, expected.as_bytes());
+
+        // Disabled by default.
+        let mut writer = BiWriter::new(Vec::new());
+        writer.write_field_default(&field).unwrap();
+        assert_eq!(writer.finish().unwrap(), b":i count 42\n");
+    }
+
     #[test]
     fn test_invalid_field_name() {
         let mut buf = Vec::new();
@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use regex::bytes::Regex;
+use serde::{Deserialize, Deserializer};
+
+/// How a replayed output stream is checked.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "OutputCheckValue")]
+pub enum OutputCheck {
+    /// Byte-for-byte equal to the snapshot.
+    #[default]
+    Exact,
+    /// Not checked.
+    Ignore,
+    /// Same lines as the snapshot, in any order.
+    Unordered,
+    /// Contains the text, regardless of the snapshot.
+    Contains(String),
+    /// Matches the regex, regardless of the snapshot.
+    Regex(Pattern),
+}
+
+impl OutputCheck {
+    /// Parses an annotation value: `exact`, `ignore`, `unordered`, `contains <text>`, or
+    /// `regex <pattern>`.
+    pub fn parse(value: &str) -> Result<Self> {
+        let (mode, argument) = value.split_once(' ').unwrap_or((value, ""));
+        Ok(match (mode, argument) {
+            ("exact", "") => Self::Exact,
+            ("ignore", "") => Self::Ignore,
+            ("unordered", "") => Self::Unordered,
+            ("contains", text) => Self::contains(text.to_owned())?,
+            ("regex", pattern) if !pattern.is_empty() => Self::Regex(Pattern::new(pattern)?),
+            _ => anyhow::bail!("`{}` is not a comparison mode", value),
+        })
+    }
+
+    /// Checks the stream contains `text`, which can't be empty.
+    fn contains(text: String) -> Result<Self> {
+        match text.is_empty() {
+            true => anyhow::bail!("`contains` needs a non-empty text"),
+            false => Ok(Self::Contains(text)),
+        }
+    }
+}
+
+/// [`OutputCheck`] as written in a tests.toml, validated like annotation values.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum OutputCheckValue {
+    Exact,
+    Ignore,
+    Unordered,
+    Contains(String),
+    Regex(Pattern),
+}
+
+impl TryFrom<OutputCheckValue> for OutputCheck {
+    type Error = anyhow::Error;
+
+    fn try_from(value: OutputCheckValue) -> Result<Self> {
+        Ok(match value {
+            OutputCheckValue::Exact => Self::Exact,
+            OutputCheckValue::Ignore => Self::Ignore,
+            OutputCheckValue::Unordered => Self::Unordered,
+            OutputCheckValue::Contains(text) => Self::contains(text)?,
+            OutputCheckValue::Regex(pattern) => Self::Regex(pattern),
+        })
+    }
+}
+
+/// How a replayed exit code is checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExitCheck {
+    /// Equal to the snapshot.
+    #[default]
+    Exact,
+    /// Not checked, a timeout is still reported.
+    Ignore,
+}
+
+impl ExitCheck {
+    /// Parses an annotation value: `exact` or `ignore`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "exact" => Ok(Self::Exact),
+            "ignore" => Ok(Self::Ignore),
+            _ => anyhow::bail!("`{}` is not a comparison mode", value),
+        }
+    }
+}
+
+/// Regex compared by its source, so tests holding one stay comparable.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        Regex::new(pattern)
+            .map(Self)
+            .with_context(|| format!("invalid regex `{}`", pattern))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_match(&self, bytes: &[u8]) -> bool {
+        self.0.is_match(bytes)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let pattern = String::deserialize(deserializer)?;
+        Pattern::new(&pattern).map_err(|e| serde::de::Error::custom(format!("{:#}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(OutputCheck::parse("ignore").unwrap(), OutputCheck::Ignore);
+        assert_eq!(
+            OutputCheck::parse("contains two words").unwrap(),
+            OutputCheck::Contains("two words".to_owned())
+        );
+        assert_eq!(
+            OutputCheck::parse(r"regex ^v\d+").unwrap(),
+            OutputCheck::Regex(Pattern::new(r"^v\d+").unwrap())
+        );
+        assert!(OutputCheck::parse("contains").is_err());
+        assert!(OutputCheck::parse("regex (").is_err());
+        assert!(OutputCheck::parse("ignore all").is_err());
+        assert_eq!(ExitCheck::parse("ignore").unwrap(), ExitCheck::Ignore);
+        assert!(ExitCheck::parse("any").is_err());
+    }
+}
@@ -1,5 +1,9 @@
-use crate::constants::{CONFIG_PATH, HISTORY};
+use crate::constants::{CONFIG_PATH, HISTORY, JOBS};
+use crate::report::{parse_report, ReportTarget};
+use crate::selection::{parse_index_ranges, IndexRanges};
+use crate::style::ColorChoice;
 use clap::{Parser, Subcommand};
+use regex::Regex;
 use std::path::PathBuf;
 
 /// Rere arguments.
@@ -14,14 +18,56 @@ pub struct Args {
     pub command: Command,
 }
 
+/// Arguments shared by the record and replay subcommands.
+#[derive(clap::Args, Debug, Default)]
+pub struct RunArgs {
+    /// Number of commands to run concurrently, 0 for one per CPU [default: config `jobs`].
+    #[clap(long, short, value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// Only run commands whose command or name matches the regex.
+    #[clap(long, value_name = "REGEX")]
+    pub filter: Option<Regex>,
+
+    /// Only run the commands at these 1-based positions, e.g. `3,7-9`.
+    #[clap(long, value_name = "LIST", value_parser = parse_index_ranges)]
+    pub index: Option<IndexRanges>,
+
+    /// Skip commands whose command or name matches the regex.
+    #[clap(long, value_name = "REGEX")]
+    pub exclude: Option<Regex>,
+
+    /// Only run commands with the tag, can be repeated to allow several tags.
+    #[clap(long, value_name = "TAG")]
+    pub tag: Vec<String>,
+
+    /// Only run the named suite from the config, can be repeated to run several suites.
+    #[clap(long, value_name = "NAME")]
+    pub suite: Vec<String>,
+}
+
 /// Rere subcommands.
 #[derive(Subcommand, Debug)]
 pub enum Command {
     /// Record shell command args.
-    Record,
+    Record {
+        #[clap(flatten)]
+        run: RunArgs,
+    },
 
     /// Replay and verify shell commands against recorded snapshot.
-    Replay,
+    Replay {
+        #[clap(flatten)]
+        run: RunArgs,
+
+        /// When to color the differences.
+        #[clap(long, value_name = "WHEN", value_enum, default_value_t)]
+        color: ColorChoice,
+
+        /// Also write the results to a report, e.g. `junit=report.xml`.
+        #[clap(long, value_name = "FORMAT=PATH", value_parser = parse_report)]
+        report: Option<ReportTarget>,
+    },
 
     /// Initialize a new rere config.
     Init {
@@ -38,6 +84,10 @@ pub enum Command {
         #[clap(long, value_name = "NUM", default_value_t = HISTORY, hide_default_value = true)]
         history: usize,
 
+        /// Set default number of commands to run concurrently, 0 for one per CPU [default: 1].
+        #[clap(long, value_name = "N", default_value_t = JOBS, hide_default_value = true)]
+        jobs: usize,
+
         /// Set overwrite default for record command [default: true].
         #[clap(long, value_parser)]
         overwrite: Option<bool>,
@@ -1,9 +1,14 @@
-use crate::constants::{FAILFAST, HISTORY, OVERWRITE, SNAPSHOT_DIR, TEST_FILE};
+use crate::constants::{
+    DIFF_CONTEXT, FAILFAST, HISTORY, INTERPRETER, JOBS, OVERWRITE, PASS_ENV, SNAPSHOT_DIR,
+    TEST_FILE,
+};
+use crate::diff::Hunk;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fs;
 use std::path::{Path, PathBuf};
-use std::{fs, u64};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
@@ -14,6 +19,16 @@ pub struct Config {
     #[serde(default)]
     pub replay: ReplayConfig,
     #[serde(default)]
+    pub shell: ShellConfig,
+    #[serde(default)]
+    pub env: EnvConfig,
+    #[serde(default)]
+    pub normalize: NormalizeConfig,
+    /// Named test files, relative to the config file directory, each recorded into its own
+    /// snapshot. When set, they replace `common.test_file`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub suites: BTreeMap<String, PathBuf>,
+    #[serde(default)]
     pub state: StateConfig,
 }
 
@@ -22,26 +37,187 @@ pub struct CommonConfig {
     pub test_file: PathBuf,
     pub snapshot_dir: PathBuf,
     pub history: usize,
+    #[serde(default = "default_jobs")]
+    pub jobs: usize,
+    /// Default per-command timeout in seconds, no timeout when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<f64>,
+    /// Whether commands run from the project root (the config file directory's parent).
+    #[serde(default)]
+    pub run_from_root: bool,
+    /// Directory commands run from, relative to the config file directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workdir: Option<PathBuf>,
+}
+
+fn default_jobs() -> usize {
+    JOBS
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct RecordConfig {
     pub overwrite: bool,
+    #[serde(default)]
+    pub compress: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct ReplayConfig {
     pub fail_fast: bool,
+    /// Number of unchanged lines shown around each change in output diffs.
+    #[serde(default = "default_context")]
+    pub context: usize,
+}
+
+fn default_context() -> usize {
+    DIFF_CONTEXT
+}
+
+/// How test commands are launched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShellConfig {
+    /// Interpreter and arguments the command is appended to, an empty list runs the command
+    /// directly (split into arguments with shell quoting rules).
+    #[serde(default = "default_interpreter")]
+    pub interpreter: Vec<String>,
+    /// Prefix applied to every command, e.g. `["valgrind", "-q"]`.
+    #[serde(default)]
+    pub wrapper: Vec<String>,
+}
+
+fn default_interpreter() -> Vec<String> {
+    INTERPRETER.iter().map(|arg| arg.to_string()).collect()
+}
+
+impl Default for ShellConfig {
+    fn default() -> Self {
+        Self {
+            interpreter: default_interpreter(),
+            wrapper: Vec::new(),
+        }
+    }
+}
+
+/// Environment variables the test commands run with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvConfig {
+    /// Whether commands start from an empty environment instead of inheriting rere's.
+    #[serde(default)]
+    pub clear: bool,
+    /// Variables passed through from rere's environment when `clear` is set.
+    #[serde(default = "default_pass")]
+    pub pass: Vec<String>,
+    /// Fixed values applied to every command.
+    #[serde(default)]
+    pub set: BTreeMap<String, String>,
+}
+
+fn default_pass() -> Vec<String> {
+    PASS_ENV.iter().map(|name| name.to_string()).collect()
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            clear: false,
+            pass: default_pass(),
+            set: BTreeMap::new(),
+        }
+    }
+}
+
+/// Rewrites applied to stdout and stderr before they are recorded or compared, see
+/// [`Normalizer`](crate::normalize::Normalizer).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct NormalizeConfig {
+    /// Built-in rules, applied before the custom ones.
+    #[serde(default)]
+    pub builtin: Vec<BuiltinRule>,
+    /// Custom rules, applied in order.
+    #[serde(default)]
+    pub rule: Vec<NormalizeRule>,
+}
+
+/// Built-in normalization rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuiltinRule {
+    /// ISO 8601 dates and date-times, replaced with `<DATE>`.
+    IsoDate,
+    /// UUIDs, replaced with `<UUID>`.
+    Uuid,
+    /// The `$HOME` directory, replaced with `<HOME>`.
+    Home,
+    /// Paths in the system temp directory, replaced with `<TMP>` up to the first component.
+    TempDir,
+}
+
+impl BuiltinRule {
+    /// Looks up a built-in rule by its config name, e.g. `iso-date`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "iso-date" => Some(Self::IsoDate),
+            "uuid" => Some(Self::Uuid),
+            "home" => Some(Self::Home),
+            "temp-dir" => Some(Self::TempDir),
+            _ => None,
+        }
+    }
+}
+
+/// Custom normalization rule, every match of `pattern` is replaced with `replacement`, which can
+/// refer to capture groups as `$1` or `${name}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NormalizeRule {
+    pub pattern: String,
+    pub replacement: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct ReplayDiff {
+    /// Suite of the test, if the config declares suites.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suite: Option<String>,
     pub shell: String,
+    /// Name of the test, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub field: String,
+    /// Output stream the difference is in, `None` for other fields.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<Stream>,
     #[serde(with = "diff_content")]
     pub expected: DiffContent,
     #[serde(with = "diff_content")]
     pub actual: DiffContent,
+    /// Actual output before normalization, if normalization changed it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Vec<String>>,
+    /// Why the outputs differ, shown above the diff: how a stream failed its comparison mode, or
+    /// where binary outputs first differ and how their size changed, `expected` and `actual`
+    /// being their hexdumps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Unified diff of `expected` and `actual` when they're lines.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hunks: Vec<Hunk>,
+}
+
+/// Output stream of a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -97,6 +273,18 @@ pub struct StateConfig {
     pub replay_elapsed_time: Vec<Duration>,
     pub replay_results: Vec<ReplayResult>,
     pub replay_diffs: Vec<Vec<ReplayDiff>>,
+    /// Latest snapshots of each named suite, `latest_snapshots` holds the default suite's.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub suite_snapshots: BTreeMap<String, Vec<PathBuf>>,
+}
+
+/// A test file recorded into its own snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suite {
+    /// Name from the `[suites]` table, `None` for `common.test_file`.
+    pub name: Option<String>,
+    /// Path relative to the config file directory.
+    pub test_file: PathBuf,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
@@ -112,13 +300,23 @@ impl Default for Config {
                 test_file: PathBuf::from(TEST_FILE),
                 snapshot_dir: PathBuf::from(SNAPSHOT_DIR),
                 history: HISTORY,
+                jobs: JOBS,
+                timeout: None,
+                run_from_root: false,
+                workdir: None,
             },
             record: RecordConfig {
                 overwrite: OVERWRITE,
+                compress: false,
             },
             replay: ReplayConfig {
                 fail_fast: FAILFAST,
+                context: DIFF_CONTEXT,
             },
+            shell: ShellConfig::default(),
+            env: EnvConfig::default(),
+            normalize: NormalizeConfig::default(),
+            suites: BTreeMap::new(),
             state: StateConfig::default(),
         }
     }
@@ -140,6 +338,37 @@ impl Config {
         Ok(())
     }
 
+    /// Resolves the directory commands run from, `None` meaning the directory `rere` was invoked
+    /// from.
+    ///
+    /// ### Parameters
+    /// - `config_path`: Path to the config file, `workdir` is relative to its directory.
+    pub fn resolve_workdir(&self, config_path: &Path) -> Result<Option<PathBuf>> {
+        let base = match (&self.common.workdir, self.common.run_from_root) {
+            (Some(_), true) => {
+                anyhow::bail!("`workdir` and `run_from_root` can't both be set")
+            }
+            (None, false) => return Ok(None),
+            _ => config_path.parent().unwrap(),
+        };
+        let config_dir = std::env::current_dir()?
+            .join(base)
+            .canonicalize()
+            .context("Failed to resolve the config file directory")?;
+
+        let workdir = match &self.common.workdir {
+            Some(workdir) => config_dir.join(workdir),
+            None => match config_dir.parent() {
+                Some(root) => root.to_path_buf(),
+                None => anyhow::bail!("Config file directory has no parent to run from"),
+            },
+        };
+        if !workdir.is_dir() {
+            anyhow::bail!("Working directory not found at: {}", workdir.display());
+        }
+        Ok(Some(workdir))
+    }
+
     pub fn init(&self, base_dir: &Path, snapshot_dir: &Path, test_file: &Path) -> Result<()> {
         fs::create_dir_all(base_dir.join(snapshot_dir))
             .context("Failed to construct path to snapshot directory")?;
@@ -147,21 +376,66 @@ impl Config {
         Ok(())
     }
 
+    /// Resolves the suites a record or replay runs.
+    ///
+    /// ### Parameters
+    /// - `names`: Suites given with `--suite`, every suite when empty. Without a `[suites]` table
+    ///   the only suite is `common.test_file`.
+    pub fn suites(&self, names: &[String]) -> Result<Vec<Suite>> {
+        if let Some(name) = self.suites.keys().find(|name| !is_suite_name(name)) {
+            anyhow::bail!(
+                "Invalid suite name `{}`, use letters, digits, `_`, and `-`",
+                name
+            );
+        }
+        if let Some(name) = names.iter().find(|name| !self.suites.contains_key(*name)) {
+            anyhow::bail!("Unknown suite `{}`", name);
+        }
+
+        if self.suites.is_empty() {
+            return Ok(vec![Suite {
+                name: None,
+                test_file: self.common.test_file.clone(),
+            }]);
+        }
+        Ok(self
+            .suites
+            .iter()
+            .filter(|(name, _)| names.is_empty() || names.contains(name))
+            .map(|(name, test_file)| Suite {
+                name: Some(name.clone()),
+                test_file: test_file.clone(),
+            })
+            .collect())
+    }
+
+    /// Stores the snapshots written by a record.
+    ///
+    /// ### Parameters
+    /// - `config_path`: Path the config is saved to.
+    /// - `snapshots`: Suite name and snapshot filename of each recorded suite.
+    /// - `elapsed`: Duration of the record.
     pub fn update_latest_record(
         &mut self,
-        config_path: &PathBuf,
-        snapshot_path: PathBuf,
+        config_path: &Path,
+        snapshots: Vec<(Option<String>, PathBuf)>,
         elapsed: Duration,
     ) -> Result<()> {
         let timestamp = Utc::now();
+        let limit = self.common.history;
 
-        self.state.latest_snapshots.insert(0, snapshot_path);
+        for (name, snapshot_path) in snapshots {
+            let latest = match name {
+                Some(name) => self.state.suite_snapshots.entry(name).or_default(),
+                None => &mut self.state.latest_snapshots,
+            };
+            latest.insert(0, snapshot_path);
+            latest.truncate(limit);
+        }
         self.state.record_timestamps.insert(0, timestamp);
         self.state.record_elapsed_time.insert(0, elapsed);
 
         // Trim to history limit
-        let limit = self.common.history;
-        self.state.latest_snapshots.truncate(limit);
         self.state.record_timestamps.truncate(limit);
         self.state.record_elapsed_time.truncate(limit);
 
@@ -170,7 +444,7 @@ impl Config {
 
     pub fn update_latest_replay(
         &mut self,
-        config_path: &PathBuf,
+        config_path: &Path,
         elapsed: Duration,
         result: ReplayResult,
         diff: Vec<ReplayDiff>,
@@ -192,16 +466,28 @@ impl Config {
         self.save(config_path)
     }
 
-    pub fn get_latest_snapshot(&self) -> Option<&PathBuf> {
-        self.state.latest_snapshots.first()
+    pub fn get_latest_snapshot(&self, suite: &Suite) -> Option<&PathBuf> {
+        match &suite.name {
+            Some(name) => self.state.suite_snapshots.get(name)?.first(),
+            None => self.state.latest_snapshots.first(),
+        }
     }
 }
 
+/// Whether a suite name is usable as a snapshot filename.
+fn is_suite_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
 pub fn init_config(
     config_path: &Path,
     test_file: Option<PathBuf>,
     snapshot_dir: Option<PathBuf>,
     history: usize,
+    jobs: usize,
     overwrite: Option<bool>,
     fail_fast: Option<bool>,
 ) -> Result<Config> {
@@ -214,6 +500,7 @@ pub fn init_config(
 
     let mut config = Config::default();
     config.common.history = history;
+    config.common.jobs = jobs;
 
     if let Some(test_file) = test_file {
         config.common.test_file = test_file;
@@ -273,7 +560,7 @@ mod vec_duration {
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
     /// Serializes a `Vec<Duration>` by converting each `Duration` to a `SerializableDuration`.
-    pub fn serialize<S>(times: &Vec<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+    pub fn serialize<S>(times: &[Duration], serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
@@ -6,7 +6,17 @@ pub const TEST_FILE: &str = "test.list";
 pub const SNAPSHOT_DIR: &str = "snapshots";
 /// Default history logging.
 pub const HISTORY: usize = 3;
+/// Default number of commands run concurrently.
+pub const JOBS: usize = 1;
+/// Default interpreter the test commands are passed to.
+pub const INTERPRETER: [&str; 2] = ["sh", "-c"];
+/// Default variables passed through to commands run in a cleared environment.
+pub const PASS_ENV: [&str; 1] = ["PATH"];
 /// Default for overwrite arg.
 pub const OVERWRITE: bool = true;
 /// Default for fail fast arg.
 pub const FAILFAST: bool = true;
+/// Default number of unchanged lines shown around each change in a replay diff.
+pub const DIFF_CONTEXT: usize = 3;
+/// Minimum stdout/stderr size in bytes compressed when `record.compress` is enabled.
+pub const COMPRESS_THRESHOLD: usize = 1024;
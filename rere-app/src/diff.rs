@@ -0,0 +1,477 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Line added to the side of a diff whose output doesn't end with a newline when the other does.
+pub const NO_NEWLINE: &str = "\\ No newline at end of file";
+/// Edit distance above which lines are no longer matched up, the differing parts are reported
+/// as replaced instead. Bounds the memory the diff takes on unrelated outputs.
+const MAX_EDIT_DISTANCE: usize = 1000;
+/// Bytes per hexdump row.
+const HEXDUMP_WIDTH: usize = 16;
+
+/// A group of changed lines with the unchanged lines around them, as in a unified diff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hunk {
+    /// 1-based first expected line, or the line before when the hunk has none.
+    pub expected_start: usize,
+    pub expected_len: usize,
+    /// 1-based first actual line, or the line before when the hunk has none.
+    pub actual_start: usize,
+    pub actual_len: usize,
+    /// Lines prefixed with ` ` (unchanged), `-` (expected only), or `+` (actual only).
+    pub lines: Vec<String>,
+}
+
+impl Hunk {
+    /// The `@@ -1,3 +1,4 @@` header of the hunk.
+    pub fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.expected_start, self.expected_len, self.actual_start, self.actual_len
+        )
+    }
+}
+
+/// Splits output into lines, keeping any `\r` so line ending changes show up in the diff.
+pub fn output_lines(bytes: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(bytes)
+        .split_terminator('\n')
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Lines of the expected and actual output, with [`NO_NEWLINE`] added to the side missing the
+/// final newline the other has.
+pub fn output_line_pair(expected: &[u8], actual: &[u8]) -> (Vec<String>, Vec<String>) {
+    let (mut expected_lines, mut actual_lines) = (output_lines(expected), output_lines(actual));
+    let newline = |bytes: &[u8]| bytes.is_empty() || bytes.ends_with(b"\n");
+    if newline(expected) != newline(actual) {
+        match newline(expected) {
+            true => actual_lines.push(NO_NEWLINE.to_owned()),
+            false => expected_lines.push(NO_NEWLINE.to_owned()),
+        }
+    }
+    (expected_lines, actual_lines)
+}
+
+/// Whether output is binary, i.e. not valid UTF-8 or holding NUL bytes, and compared as a
+/// hexdump rather than as lines.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.contains(&0) || std::str::from_utf8(bytes).is_err()
+}
+
+/// Rows of a hexdump of `bytes`: the offset, the bytes in hex, and the printable ASCII ones, e.g.
+/// `00000000  68 69 0a  |hi.|`.
+pub fn hexdump(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(HEXDUMP_WIDTH)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&byte| match byte {
+                    b' '..=b'~' => byte as char,
+                    _ => '.',
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<width$}  |{}|",
+                row * HEXDUMP_WIDTH,
+                hex.join(" "),
+                ascii,
+                width = HEXDUMP_WIDTH * 3 - 1
+            )
+        })
+        .collect()
+}
+
+/// Lines to diff two outputs by: hexdumps when either is binary, otherwise the lines of
+/// [`output_line_pair`].
+pub fn output_diff_lines(expected: &[u8], actual: &[u8]) -> (Vec<String>, Vec<String>) {
+    match is_binary(expected) || is_binary(actual) {
+        true => (hexdump(expected), hexdump(actual)),
+        false => output_line_pair(expected, actual),
+    }
+}
+
+/// Lines of an output on its own, its hexdump when binary.
+pub fn display_lines(bytes: &[u8]) -> Vec<String> {
+    match is_binary(bytes) {
+        true => hexdump(bytes),
+        false => output_lines(bytes),
+    }
+}
+
+/// Where two differing outputs first differ and how their size changed, `None` unless either is
+/// binary.
+pub fn binary_summary(expected: &[u8], actual: &[u8]) -> Option<String> {
+    if !is_binary(expected) && !is_binary(actual) {
+        return None;
+    }
+    let first = expected
+        .iter()
+        .zip(actual)
+        .take_while(|(e, a)| e == a)
+        .count();
+    let size = match actual.len() as i128 - expected.len() as i128 {
+        0 => format!("size unchanged at {} bytes", expected.len()),
+        change => format!(
+            "{} -> {} bytes ({:+})",
+            expected.len(),
+            actual.len(),
+            change
+        ),
+    };
+    Some(format!(
+        "Binary output differs from byte {} (0x{:x}), {}",
+        first, first, size
+    ))
+}
+
+/// Byte ranges of the words that differ between two versions of a line, for highlighting
+/// changes within it. Words are runs of alphanumeric characters or of whitespace, any other
+/// character is a word of its own.
+pub fn changed_ranges(expected: &str, actual: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let (expected_words, actual_words) = (words(expected), words(actual));
+    let expected_text: Vec<&str> = expected_words
+        .iter()
+        .map(|r| &expected[r.clone()])
+        .collect();
+    let actual_text: Vec<&str> = actual_words.iter().map(|r| &actual[r.clone()]).collect();
+
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let (mut e, mut a) = (0, 0);
+    let extend = |ranges: &mut Vec<Range<usize>>, word: &Range<usize>| match ranges.last_mut() {
+        Some(last) if last.end == word.start => last.end = word.end,
+        _ => ranges.push(word.clone()),
+    };
+    for edit in edit_script(&expected_text, &actual_text) {
+        match edit {
+            Edit::Equal => (e, a) = (e + 1, a + 1),
+            Edit::Delete => {
+                extend(&mut removed, &expected_words[e]);
+                e += 1;
+            }
+            Edit::Insert => {
+                extend(&mut added, &actual_words[a]);
+                a += 1;
+            }
+        }
+    }
+    (removed, added)
+}
+
+/// Byte ranges of the words of `line`, see [`changed_ranges`].
+fn words(line: &str) -> Vec<Range<usize>> {
+    let class = |c: char| match c {
+        c if c.is_alphanumeric() || c == '_' => 0,
+        c if c.is_whitespace() => 1,
+        _ => 2,
+    };
+    let mut words: Vec<Range<usize>> = Vec::new();
+    let mut previous = None;
+    for (i, c) in line.char_indices() {
+        let end = i + c.len_utf8();
+        match words.last_mut() {
+            Some(word) if class(c) != 2 && previous == Some(class(c)) => word.end = end,
+            _ => words.push(i..end),
+        }
+        previous = Some(class(c));
+    }
+    words
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Unified diff of the expected and actual lines, empty when they're equal.
+///
+/// ### Parameters
+/// - `expected`: Lines from the snapshot.
+/// - `actual`: Lines from the replay.
+/// - `context`: Number of unchanged lines kept around each change, changes closer than twice
+///   this share a hunk.
+pub fn unified_diff(expected: &[String], actual: &[String], context: usize) -> Vec<Hunk> {
+    let edits = edit_script(expected, actual);
+
+    let mut hunks = Vec::new();
+    // Expected and actual position before each edit.
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut e, mut a) = (0, 0);
+    for edit in &edits {
+        positions.push((e, a));
+        match edit {
+            Edit::Equal => (e, a) = (e + 1, a + 1),
+            Edit::Delete => e += 1,
+            Edit::Insert => a += 1,
+        }
+    }
+    positions.push((e, a));
+
+    let mut i = 0;
+    while i < edits.len() {
+        if edits[i] == Edit::Equal {
+            i += 1;
+            continue;
+        }
+        let start = i.saturating_sub(context);
+        let mut last_change = i;
+        let mut j = i;
+        while j < edits.len() {
+            if edits[j] != Edit::Equal {
+                last_change = j;
+            } else if j - last_change > 2 * context {
+                break;
+            }
+            j += 1;
+        }
+        let end = (last_change + context + 1).min(edits.len());
+
+        let lines: Vec<String> = (start..end)
+            .map(|k| {
+                let (e, a) = positions[k];
+                match edits[k] {
+                    Edit::Equal => format!(" {}", expected[e]),
+                    Edit::Delete => format!("-{}", expected[e]),
+                    Edit::Insert => format!("+{}", actual[a]),
+                }
+            })
+            .collect();
+        let (e, a) = positions[start];
+        let expected_len = positions[end].0 - e;
+        let actual_len = positions[end].1 - a;
+        hunks.push(Hunk {
+            expected_start: if expected_len == 0 { e } else { e + 1 },
+            expected_len,
+            actual_start: if actual_len == 0 { a } else { a + 1 },
+            actual_len,
+            lines,
+        });
+        i = end;
+    }
+    hunks
+}
+
+/// Shortest edit script turning `expected` into `actual`.
+fn edit_script<T: PartialEq>(expected: &[T], actual: &[T]) -> Vec<Edit> {
+    let prefix = expected
+        .iter()
+        .zip(actual)
+        .take_while(|(e, a)| e == a)
+        .count();
+    let suffix = expected[prefix..]
+        .iter()
+        .rev()
+        .zip(actual[prefix..].iter().rev())
+        .take_while(|(e, a)| e == a)
+        .count();
+    let a = &expected[prefix..expected.len() - suffix];
+    let b = &actual[prefix..actual.len() - suffix];
+
+    let mut edits = vec![Edit::Equal; prefix];
+    match myers(a, b) {
+        Some(middle) => edits.extend(middle),
+        None => {
+            edits.extend(std::iter::repeat_n(Edit::Delete, a.len()));
+            edits.extend(std::iter::repeat_n(Edit::Insert, b.len()));
+        }
+    }
+    edits.extend(std::iter::repeat_n(Edit::Equal, suffix));
+    edits
+}
+
+/// Myers' O(ND) diff, `None` when the edit distance exceeds [`MAX_EDIT_DISTANCE`].
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Option<Vec<Edit>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (a.len() + b.len()).min(MAX_EDIT_DISTANCE) as isize;
+    let offset = max + 1;
+    // Furthest x reached on each diagonal k = x - y, indexed by k + offset.
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    // `v` on diagonals -d..=d before round d, for backtracking.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let forward = |v: &[isize], k: isize, d: isize| {
+        k == -d || (k != d && v[(k - 1 + offset) as usize] < v[(k + 1 + offset) as usize])
+    };
+
+    let mut found = false;
+    'search: for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = match forward(&v, k, d) {
+                true => v[(k + 1 + offset) as usize],
+                false => v[(k - 1 + offset) as usize] + 1,
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(k + offset) as usize] = x;
+            if x >= n && y >= m {
+                found = true;
+                break 'search;
+            }
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let previous_k = match k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            true => k + 1,
+            false => k - 1,
+        };
+        let (previous_x, previous_y) = match d {
+            0 => (0, 0),
+            _ => (at(previous_k), at(previous_k) - previous_k),
+        };
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal);
+        }
+        if d > 0 {
+            match x == previous_x {
+                true => edits.push(Edit::Insert),
+                false => edits.push(Edit::Delete),
+            }
+        }
+        (x, y) = (previous_x, previous_y);
+    }
+    edits.reverse();
+    Some(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let expected = lines("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl");
+        let actual = lines("a\nb\nc\nx\nd\ne\nf\ng\nh\ni\nj\nK\nl");
+        let hunks = unified_diff(&expected, &actual, 1);
+        assert_eq!(
+            hunks,
+            vec![
+                Hunk {
+                    expected_start: 3,
+                    expected_len: 2,
+                    actual_start: 3,
+                    actual_len: 3,
+                    lines: lines(" c\n+x\n d"),
+                },
+                Hunk {
+                    expected_start: 10,
+                    expected_len: 3,
+                    actual_start: 11,
+                    actual_len: 3,
+                    lines: lines(" j\n-k\n+K\n l"),
+                },
+            ]
+        );
+        assert_eq!(hunks[0].header(), "@@ -3,2 +3,3 @@");
+
+        // Nearby changes share a hunk.
+        let hunks = unified_diff(&expected, &actual, 4);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].lines.len(), 14);
+
+        assert!(unified_diff(&expected, &expected, 3).is_empty());
+    }
+
+    #[test]
+    fn test_empty_sides() {
+        let hunks = unified_diff(&[], &lines("a\nb"), 3);
+        assert_eq!(hunks[0].header(), "@@ -0,0 +1,2 @@");
+        assert_eq!(hunks[0].lines, lines("+a\n+b"));
+        let hunks = unified_diff(&lines("a"), &[], 3);
+        assert_eq!(hunks[0].header(), "@@ -1,1 +0,0 @@");
+    }
+
+    #[test]
+    fn test_output_line_pair() {
+        let (expected, actual) = output_line_pair(b"a\r\nb\n", b"a\nb");
+        assert_eq!(expected, ["a\r", "b"]);
+        assert_eq!(actual, ["a", "b", NO_NEWLINE]);
+        let (expected, actual) = output_line_pair(b"", b"a\n");
+        assert_eq!((expected.len(), actual.len()), (0, 1));
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let bytes: Vec<u8> = (0..18).map(|i| b'a' + i).chain([0, 0xff]).collect();
+        assert_eq!(
+            hexdump(&bytes),
+            [
+                "00000000  61 62 63 64 65 66 67 68 69 6a 6b 6c 6d 6e 6f 70  |abcdefghijklmnop|",
+                "00000010  71 72 00 ff                                      |qr..|",
+            ]
+        );
+        assert!(hexdump(b"").is_empty());
+    }
+
+    #[test]
+    fn test_binary_output() {
+        assert!(is_binary(b"\x89PNG\r\n"));
+        assert!(is_binary(b"a\0b"));
+        assert!(!is_binary("héllo\n".as_bytes()));
+
+        let (expected, actual) = output_diff_lines(b"ok\n", b"ok\n\xff");
+        assert_eq!(expected, hexdump(b"ok\n"));
+        assert!(actual[0].starts_with("00000000  6f 6b 0a ff  "));
+        assert_eq!(
+            binary_summary(b"ok\n", b"ok\n\xff").unwrap(),
+            "Binary output differs from byte 3 (0x3), 3 -> 4 bytes (+1)"
+        );
+        assert_eq!(
+            binary_summary(b"\0\x01", b"\0\x02").unwrap(),
+            "Binary output differs from byte 1 (0x1), size unchanged at 2 bytes"
+        );
+        assert_eq!(binary_summary(b"a\n", b"b\n"), None);
+    }
+
+    #[test]
+    fn test_changed_ranges() {
+        let (removed, added) = changed_ranges("let total = 42;", "let totals = 42 ;");
+        assert_eq!(removed, vec![4..9]);
+        assert_eq!(added, [4..10, 15..16]);
+        assert_eq!(
+            changed_ranges("same line", "same line"),
+            (Vec::new(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_edit_script_is_minimal() {
+        let expected = lines("a\nb\nc\na\nb\nb\na");
+        let actual = lines("c\nb\na\nb\na\nc");
+        let edits = edit_script(&expected, &actual);
+        let changes = edits.iter().filter(|edit| **edit != Edit::Equal).count();
+        assert_eq!(changes, 5);
+
+        // Unrelated outputs beyond the edit distance limit are replaced wholesale.
+        let expected: Vec<String> = (0..MAX_EDIT_DISTANCE).map(|i| i.to_string()).collect();
+        let actual: Vec<String> = (0..MAX_EDIT_DISTANCE).map(|i| format!("x{}", i)).collect();
+        let edits = edit_script(&expected, &actual);
+        assert_eq!(edits.len(), 2 * MAX_EDIT_DISTANCE);
+        assert!(edits[..MAX_EDIT_DISTANCE]
+            .iter()
+            .all(|e| *e == Edit::Delete));
+    }
+}
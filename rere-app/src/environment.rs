@@ -0,0 +1,147 @@
+use crate::config::EnvConfig;
+use bi_parser::prelude::Crc32;
+use std::collections::{BTreeMap, BTreeSet};
+use std::process::Command;
+
+/// The environment a single test runs with.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Environment {
+    /// Whether the command starts from an empty environment.
+    pub clear: bool,
+    /// Variables set by rere, on top of the inherited environment unless `clear` is set.
+    pub vars: BTreeMap<String, String>,
+    /// Names of the variables passed through from the inherited environment when `clear` is set,
+    /// their values in `vars` come from the current session.
+    pub passed: BTreeSet<String>,
+}
+
+impl Environment {
+    /// Resolves the environment of a test from the config and the test's own overrides, which
+    /// take precedence over the config's fixed values.
+    ///
+    /// ### Parameters
+    /// - `config`: Environment settings from the config.
+    /// - `overrides`: Variables set for this test only.
+    pub fn resolve(config: &EnvConfig, overrides: &[(String, String)]) -> Self {
+        let mut vars = BTreeMap::new();
+        let mut passed = BTreeSet::new();
+        if config.clear {
+            for name in &config.pass {
+                if let Ok(value) = std::env::var(name) {
+                    vars.insert(name.clone(), value);
+                }
+                passed.insert(name.clone());
+            }
+        }
+        vars.extend(config.set.clone());
+        vars.extend(overrides.iter().cloned());
+        passed.retain(|name| {
+            !config.set.contains_key(name) && !overrides.iter().any(|(set, _)| set == name)
+        });
+
+        Self {
+            clear: config.clear,
+            vars,
+            passed,
+        }
+    }
+
+    /// Applies the environment to `command`.
+    pub fn apply(&self, command: &mut Command) {
+        if self.clear {
+            command.env_clear();
+        }
+        command.envs(&self.vars);
+    }
+
+    /// CRC-32 of the variables set by rere (and whether the environment was cleared). Inherited
+    /// values are left out, they differ between every terminal session and machine, so passed
+    /// through variables only contribute their names.
+    pub fn digest(&self) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(&[self.clear as u8]);
+        for (name, value) in &self.vars {
+            if self.passed.contains(name) {
+                continue;
+            }
+            crc.update(name.as_bytes());
+            crc.update(b"=");
+            crc.update(value.as_bytes());
+            crc.update(b"\0");
+        }
+        for name in &self.passed {
+            crc.update(name.as_bytes());
+            crc.update(b"\0");
+        }
+        crc.value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(clear: bool, set: &[(&str, &str)]) -> EnvConfig {
+        EnvConfig {
+            clear,
+            pass: vec!["PATH".to_owned(), "RERE_UNSET_VARIABLE".to_owned()],
+            set: set
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let overrides = vec![("TZ".to_owned(), "Asia/Tokyo".to_owned())];
+
+        let inherited = Environment::resolve(&config(false, &[("TZ", "UTC")]), &overrides);
+        assert!(!inherited.clear);
+        assert_eq!(inherited.vars.len(), 1);
+        assert_eq!(inherited.vars["TZ"], "Asia/Tokyo");
+
+        let cleared = Environment::resolve(&config(true, &[("LANG", "C")]), &[]);
+        assert!(cleared.clear);
+        assert_eq!(cleared.vars["PATH"], std::env::var("PATH").unwrap());
+        assert_eq!(cleared.vars["LANG"], "C");
+        assert!(!cleared.vars.contains_key("RERE_UNSET_VARIABLE"));
+    }
+
+    #[test]
+    fn test_digest() {
+        let base = Environment::resolve(&config(false, &[("LANG", "C")]), &[]);
+        assert_eq!(base.digest(), base.clone().digest());
+
+        let changed = Environment::resolve(&config(false, &[("LANG", "C.UTF-8")]), &[]);
+        assert_ne!(base.digest(), changed.digest());
+
+        let cleared = Environment {
+            clear: true,
+            ..base.clone()
+        };
+        assert_ne!(base.digest(), cleared.digest());
+    }
+
+    #[test]
+    fn test_digest_ignores_passed_values() {
+        let env = Environment::resolve(&config(true, &[("LANG", "C")]), &[]);
+        assert!(env.passed.contains("PATH"));
+        let mut elsewhere = env.clone();
+        elsewhere
+            .vars
+            .insert("PATH".to_owned(), "/opt/ci/bin:/usr/bin".to_owned());
+        assert_eq!(env.digest(), elsewhere.digest());
+
+        // Setting a passed variable makes its value part of the digest.
+        let set = Environment::resolve(&config(true, &[("PATH", "/bin")]), &[]);
+        assert!(!set.passed.contains("PATH"));
+        let other = Environment::resolve(&config(true, &[("PATH", "/usr/bin")]), &[]);
+        assert_ne!(set.digest(), other.digest());
+
+        // Passing another variable changes the digest.
+        let mut more = config(true, &[("LANG", "C")]);
+        more.pass.push("HOME".to_owned());
+        assert_ne!(env.digest(), Environment::resolve(&more, &[]).digest());
+    }
+}
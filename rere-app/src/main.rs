@@ -1,9 +1,20 @@
+mod check;
 mod cli;
 mod config;
 pub mod constants;
+mod diff;
+mod environment;
+mod normalize;
 mod record;
 mod replay;
+mod report;
+mod runner;
+mod selection;
 mod shell;
+mod snapshot;
+mod style;
+mod test_list;
+mod test_toml;
 
 use anyhow::Result;
 use clap::Parser;
@@ -29,6 +40,7 @@ fn main() -> Result<()> {
             test_file,
             snapshot_dir,
             history,
+            jobs,
             overwrite,
             fail_fast,
         } => {
@@ -37,27 +49,32 @@ fn main() -> Result<()> {
                 test_file,
                 snapshot_dir,
                 history,
+                jobs,
                 overwrite,
                 fail_fast,
             )?;
             println!("Initialized config at {}", args.config.display());
         }
-        Command::Record => {
+        Command::Record { ref run } => {
             let mut config = Config::load_or_create(&args.config)?;
-            match record::record(&mut config, &args.config) {
+            match record::record(&mut config, &args.config, run) {
                 Ok(_) => println!("Recording completed successfully"),
                 Err(e) => {
-                    eprintln!("Error during recording: {}", e);
+                    eprintln!("Error during recording: {:#}", e);
                     std::process::exit(1);
                 }
             }
         }
-        Command::Replay => {
+        Command::Replay {
+            ref run,
+            color,
+            ref report,
+        } => {
             let mut config = Config::load_or_create(&args.config)?;
-            match replay::replay(&mut config, &args.config) {
+            match replay::replay(&mut config, &args.config, run, color, report.as_ref()) {
                 Ok(_) => println!("Recording completed successfully"),
                 Err(e) => {
-                    eprintln!("Error during recording: {}", e);
+                    eprintln!("Error during recording: {:#}", e);
                     std::process::exit(1);
                 }
             }
@@ -0,0 +1,181 @@
+use crate::config::{BuiltinRule, NormalizeConfig};
+use crate::shell::CommandOutput;
+use anyhow::{Context, Result};
+use regex::bytes::Regex;
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// ISO 8601 date, optionally followed by a time and an offset.
+static ISO_DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"\b\d{4}-\d{2}-\d{2}",
+        r"(?:[T ]\d{2}:\d{2}(?::\d{2}(?:[.,]\d+)?)?(?:Z|[+-]\d{2}(?::?\d{2})?)?)?\b",
+    ))
+    .unwrap()
+});
+static UUID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b[0-9a-fA-F]{8}-(?:[0-9a-fA-F]{4}-){3}[0-9a-fA-F]{12}\b").unwrap()
+});
+
+/// Compiled normalization rules, applied in order to a command's stdout and stderr.
+#[derive(Debug, Clone, Default)]
+pub struct Normalizer {
+    rules: Vec<(Regex, Vec<u8>)>,
+}
+
+impl Normalizer {
+    /// Compiles the rules of `config`.
+    pub fn new(config: &NormalizeConfig) -> Result<Self> {
+        Self::default().extend(config)
+    }
+
+    /// Normalizer applying the rules of `config`, built-in ones first, after these rules.
+    pub fn extend(&self, config: &NormalizeConfig) -> Result<Self> {
+        let mut rules = self.rules.clone();
+        for builtin in &config.builtin {
+            rules.extend(builtin_rule(*builtin));
+        }
+        for rule in &config.rule {
+            let pattern = Regex::new(&rule.pattern)
+                .with_context(|| format!("invalid normalization pattern `{}`", rule.pattern))?;
+            rules.push((pattern, rule.replacement.clone().into_bytes()));
+        }
+        Ok(Self { rules })
+    }
+
+    /// Applies every rule to `bytes`, borrowing them when nothing matches.
+    pub fn apply<'a>(&self, bytes: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut bytes = Cow::Borrowed(bytes);
+        for (pattern, replacement) in &self.rules {
+            if let Cow::Owned(replaced) = pattern.replace_all(&bytes, replacement.as_slice()) {
+                bytes = Cow::Owned(replaced);
+            }
+        }
+        bytes
+    }
+
+    /// Normalized copy of `output`, borrowing it when nothing matches.
+    pub fn normalize<'a>(&self, output: &'a CommandOutput) -> Cow<'a, CommandOutput> {
+        match (self.apply(&output.stdout), self.apply(&output.stderr)) {
+            (Cow::Borrowed(_), Cow::Borrowed(_)) => Cow::Borrowed(output),
+            (stdout, stderr) => Cow::Owned(CommandOutput {
+                shell: output.shell.clone(),
+                stdin: output.stdin.clone(),
+                returncode: output.returncode,
+                stdout: stdout.into_owned(),
+                stderr: stderr.into_owned(),
+                timed_out: output.timed_out,
+                env_digest: output.env_digest,
+            }),
+        }
+    }
+
+    /// Normalizes the stdout and stderr of `output` in place.
+    pub fn apply_output(&self, output: &mut CommandOutput) {
+        if let Cow::Owned(stdout) = self.apply(&output.stdout) {
+            output.stdout = stdout;
+        }
+        if let Cow::Owned(stderr) = self.apply(&output.stderr) {
+            output.stderr = stderr;
+        }
+    }
+}
+
+/// Pattern and replacement of a built-in rule, `None` when it has nothing to match (e.g. `$HOME`
+/// isn't set).
+fn builtin_rule(builtin: BuiltinRule) -> Option<(Regex, Vec<u8>)> {
+    let (pattern, replacement) = match builtin {
+        BuiltinRule::IsoDate => (ISO_DATE.clone(), "<DATE>"),
+        BuiltinRule::Uuid => (UUID.clone(), "<UUID>"),
+        BuiltinRule::Home => {
+            let home = std::env::var("HOME").ok()?;
+            (path_pattern(Path::new(&home), false)?, "<HOME>")
+        }
+        // The first component under the temp directory is usually a random name.
+        BuiltinRule::TempDir => (path_pattern(&std::env::temp_dir(), true)?, "<TMP>"),
+    };
+    Some((pattern, replacement.as_bytes().to_vec()))
+}
+
+/// Pattern matching `path` when it isn't the prefix of a longer name, and with `child` the
+/// component following it. `None` for an empty path or the root directory, which would match
+/// everywhere.
+fn path_pattern(path: &Path, child: bool) -> Option<Regex> {
+    let path = path.to_string_lossy();
+    let path = path.trim_end_matches('/');
+    if path.is_empty() {
+        return None;
+    }
+    let path = regex::escape(path);
+    let pattern = match child {
+        true => format!(r#"{}(?:/[^\s/'"]+|\b)"#, path),
+        false => format!(r"{}\b", path),
+    };
+    Some(Regex::new(&pattern).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NormalizeRule;
+
+    #[test]
+    fn test_builtin_rules() {
+        let normalizer = Normalizer::new(&NormalizeConfig {
+            builtin: vec![
+                BuiltinRule::IsoDate,
+                BuiltinRule::Uuid,
+                BuiltinRule::TempDir,
+            ],
+            rule: Vec::new(),
+        })
+        .unwrap();
+        let temp = std::env::temp_dir().join(".tmpAb12").join("out.txt");
+        let input = format!(
+            "at 2024-05-01T12:30:45.123Z on 2024-05-01, id 123e4567-e89b-12d3-a456-426614174000\n\
+             wrote {}\n",
+            temp.display()
+        );
+        assert_eq!(
+            normalizer.apply(input.as_bytes()),
+            b"at <DATE> on <DATE>, id <UUID>\nwrote <TMP>/out.txt\n".as_slice()
+        );
+        assert!(matches!(normalizer.apply(b"no match"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_path_pattern() {
+        let home = path_pattern(Path::new("/home/user/"), false).unwrap();
+        assert!(home.is_match(b"/home/user/file"));
+        assert!(!home.is_match(b"/home/username"));
+
+        // `HOME=/` (e.g. in containers) has nothing left to match once trimmed.
+        for path in ["/", "//", ""] {
+            assert!(path_pattern(Path::new(path), false).is_none(), "{path:?}");
+            assert!(path_pattern(Path::new(path), true).is_none(), "{path:?}");
+        }
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let config = |pattern: &str, replacement: &str| NormalizeConfig {
+            builtin: Vec::new(),
+            rule: vec![NormalizeRule {
+                pattern: pattern.to_owned(),
+                replacement: replacement.to_owned(),
+            }],
+        };
+        let normalizer = Normalizer::new(&config(r"took \d+ms", "took <N>ms")).unwrap();
+        let normalizer = normalizer
+            .extend(&config(r"pid (\d+)", "process $1"))
+            .unwrap();
+        assert_eq!(
+            normalizer.apply(b"pid 42 took 15ms"),
+            b"process 42 took <N>ms".as_slice()
+        );
+
+        let error = Normalizer::new(&config("(", "")).unwrap_err();
+        assert_eq!(error.to_string(), "invalid normalization pattern `(`");
+    }
+}
@@ -1,100 +1,144 @@
 use crate::{
-    config::Config,
-    shell::{capture, CommandOutput},
+    cli::RunArgs,
+    config::{Config, Suite},
+    runner::{run_tests, RunOptions},
+    selection::Selection,
+    shell::CommandOutput,
+    snapshot::{read_snapshot, write_snapshot, Snapshot},
+    test_list::load_test_cases,
 };
 use anyhow::Result;
-use bi_parser::prelude::*;
-use std::fs::File;
+use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
 
-pub fn record(config: &mut Config, config_path: &PathBuf) -> Result<()> {
-    let test_file = config.common.test_file.clone();
+/// Records the selected commands of each suite into the suite's snapshot. When only some
+/// commands are selected, their outputs replace the corresponding entries of the latest snapshot
+/// and the others are kept as they are. Suites without selected commands are left untouched.
+pub fn record(config: &mut Config, config_path: &Path, args: &RunArgs) -> Result<()> {
     let base_dir = config_path.parent().unwrap();
-    let test_path = base_dir.join(test_file);
 
-    // Read test list
-    let shells = load_test_commands(&test_path)?;
+    // Read test lists
+    let suites = config.suites(&args.suite)?;
+    let tests = suites
+        .iter()
+        .map(|suite| load_test_cases(&base_dir.join(&suite.test_file)))
+        .collect::<Result<Vec<_>>>()?;
+    let options = RunOptions::from_config(config, config_path, args.jobs)?;
+    let selection = Selection::from_args(args);
+    let selected = selection.select(&tests)?;
 
     // Capture outputs
     let start = std::time::Instant::now();
-    let outputs: Result<Vec<CommandOutput>> = shells.iter().map(|shell| capture(shell)).collect();
-    let outputs = outputs?;
-
-    // Generate snapshot filename with timestamp
-    let timestamp = chrono::Utc::now();
-    let snapshot_name = match config.record.overwrite {
-        true => {
-            format!("{}.bi", config.common.test_file.display())
+    let mut snapshots = Vec::new();
+    let mut unexpected_exits = 0;
+    for ((suite, tests), selected) in suites.iter().zip(&tests).zip(&selected) {
+        if selected.is_empty() {
+            continue;
         }
-        false => {
-            format!(
-                "{}_{}.bi",
-                config.common.test_file.display(),
-                timestamp.format("%Y%m%d_%H%M%S")
-            )
+        if let Some(name) = &suite.name {
+            println!("Suite: {}", name);
         }
-    };
-    let snapshot_path = base_dir
-        .join(&config.common.snapshot_dir)
-        .join(&snapshot_name);
-
-    // Write snapshot using bi-parser library
-    write_snapshot(&snapshot_path, &outputs)?;
+        let mut outputs = match selection.is_all() {
+            true => Vec::with_capacity(tests.len()),
+            false => merge_base(config, base_dir, suite, tests.len(), &options)?,
+        };
 
-    // Update config with new snapshot
-    let elapsed = chrono::Duration::from_std(start.elapsed())?;
-    config.update_latest_record(config_path, PathBuf::from(snapshot_name), elapsed)?;
+        let subset: Vec<_> = selected.iter().map(|&i| tests[i].clone()).collect();
+        let normalizers = options.normalizers(&subset)?;
+        run_tests(&subset, &options, |position, test, mut output, _| {
+            println!("Capturing: {}", test.label());
+            normalizers[position].apply_output(&mut output);
+            if output.timed_out {
+                println!("  Timed out, recorded as a timeout");
+            }
+            if let Some(code) = test.expect_exit {
+                if output.returncode != code {
+                    println!("  Expected exit code {}, got {}", code, output.returncode);
+                    unexpected_exits += 1;
+                }
+            }
+            match outputs.get_mut(selected[position]) {
+                Some(entry) => *entry = output,
+                None => outputs.push(output),
+            }
+            Ok(ControlFlow::Continue(()))
+        })?;
+        snapshots.push((suite, outputs));
+    }
 
-    Ok(())
-}
+    if unexpected_exits > 0 {
+        anyhow::bail!(
+            "{} command(s) exited with an unexpected code, the snapshot was not written",
+            unexpected_exits
+        );
+    }
 
-pub fn load_test_commands(test_path: &Path) -> Result<Vec<String>> {
-    let content = std::fs::read_to_string(test_path)?;
-    let shells: Vec<String> = content
-        .lines()
-        .filter(|line| {
-            let trimmed = line.trim();
-            !trimmed.is_empty() && !trimmed.starts_with("//")
-        })
-        .map(|s| s.trim().to_owned())
-        .collect();
+    // Write snapshots using bi-parser library, named after the suite or test file
+    let timestamp = chrono::Utc::now();
+    let mut recorded = Vec::with_capacity(snapshots.len());
+    for (suite, outputs) in snapshots {
+        let stem = match &suite.name {
+            Some(name) => name.clone(),
+            None => suite.test_file.display().to_string(),
+        };
+        let snapshot_name = match config.record.overwrite {
+            true => format!("{}.bi", stem),
+            false => format!("{}_{}.bi", stem, timestamp.format("%Y%m%d_%H%M%S")),
+        };
+        let snapshot_path = base_dir
+            .join(&config.common.snapshot_dir)
+            .join(&snapshot_name);
 
-    if shells.is_empty() {
-        anyhow::bail!("Test file is empty or contains only comments.");
+        let snapshot = Snapshot {
+            shell: Some(options.shell.clone()),
+            outputs,
+        };
+        write_snapshot(&snapshot_path, &snapshot, config.record.compress)?;
+        recorded.push((suite.name.clone(), PathBuf::from(snapshot_name)));
     }
 
-    Ok(shells)
-}
+    // Update config with new snapshots
+    let elapsed = chrono::Duration::from_std(start.elapsed())?;
+    config.update_latest_record(config_path, recorded, elapsed)?;
 
-fn write_snapshot(path: &Path, outputs: &[CommandOutput]) -> Result<()> {
-    let file = File::create(path)?;
-    let mut writer = BiWriter::new(file);
+    Ok(())
+}
 
-    // Write count of tests
-    writer.write_field_default(&BiField::Integer {
-        name: b"count".to_vec(),
-        value: outputs.len() as u64,
-    })?;
+/// Outputs of the latest snapshot, which a partial record merges its outputs into.
+fn merge_base(
+    config: &Config,
+    base_dir: &Path,
+    suite: &Suite,
+    count: usize,
+    options: &RunOptions,
+) -> Result<Vec<CommandOutput>> {
+    let Some(snapshot_filename) = config.get_latest_snapshot(suite) else {
+        anyhow::bail!(
+            "No snapshot to merge the selected commands into. Record all commands first."
+        );
+    };
+    let snapshot_path = base_dir
+        .join(&config.common.snapshot_dir)
+        .join(snapshot_filename);
+    let snapshot = read_snapshot(&snapshot_path)?;
 
-    // Write each test output
-    for output in outputs {
-        writer.write_field_default(&BiField::Blob {
-            name: b"shell".to_vec(),
-            data: output.shell.as_bytes().to_vec(),
-        })?;
-        writer.write_field_default(&BiField::SignedInteger {
-            name: b"returncode".to_vec(),
-            value: output.returncode as i64,
-        })?;
-        writer.write_field_default(&BiField::Blob {
-            name: b"stdout".to_vec(),
-            data: output.stdout.clone(),
-        })?;
-        writer.write_field_default(&BiField::Blob {
-            name: b"stderr".to_vec(),
-            data: output.stderr.clone(),
-        })?;
+    if snapshot.outputs.len() != count {
+        anyhow::bail!(
+            "Number of commands in test file ({}) doesn't match snapshot ({}). Record all \
+             commands first.",
+            count,
+            snapshot.outputs.len()
+        );
     }
-
-    Ok(())
+    if snapshot
+        .shell
+        .as_ref()
+        .is_some_and(|shell| *shell != options.shell)
+    {
+        anyhow::bail!(
+            "Snapshot was recorded with a different interpreter or wrapper. Record all commands \
+             first."
+        );
+    }
+    Ok(snapshot.outputs)
 }
@@ -1,28 +1,110 @@
 use crate::{
-    config::{Config, DiffContent, ReplayDiff, ReplayResult},
-    record::load_test_commands,
-    shell::capture,
+    check::{ExitCheck, OutputCheck},
+    cli::RunArgs,
+    config::{Config, DiffContent, ReplayDiff, ReplayResult, ShellConfig, Stream, Suite},
+    diff::{binary_summary, display_lines, output_diff_lines, output_lines, unified_diff},
+    report::{ReportTarget, SuiteReport, TestReport},
+    runner::{run_tests, RunOptions},
+    selection::Selection,
+    shell::CommandOutput,
+    snapshot::read_snapshot,
+    style::{ColorChoice, Painter},
+    test_list::{load_test_cases, TestCase},
 };
 use anyhow::Result;
-use bi_parser::prelude::*;
-use std::{fs::File, path::PathBuf, usize};
+use std::{ops::ControlFlow, path::Path};
 
-pub fn replay(config: &mut Config, config_path: &PathBuf) -> Result<()> {
-    let test_file = config.common.test_file.clone();
+/// Replays the selected commands of each suite, comparing them against the corresponding entries
+/// of the suite's latest snapshot.
+pub fn replay(
+    config: &mut Config,
+    config_path: &Path,
+    args: &RunArgs,
+    color: ColorChoice,
+    report: Option<&ReportTarget>,
+) -> Result<()> {
     let base_dir = config_path.parent().unwrap();
-    let test_path = base_dir.join(test_file);
+    let painter = Painter::new(color);
 
-    if !test_path.exists() {
-        anyhow::bail!(
-            "Test file not found at: {}. Run `rere init` first.",
-            test_path.display()
-        );
+    // Read test lists
+    let suites = config.suites(&args.suite)?;
+    let mut tests = Vec::with_capacity(suites.len());
+    for suite in &suites {
+        let test_path = base_dir.join(&suite.test_file);
+        if !test_path.exists() {
+            anyhow::bail!(
+                "Test file not found at: {}. Run `rere init` first.",
+                test_path.display()
+            );
+        }
+        tests.push(load_test_cases(&test_path)?);
+    }
+    let options = RunOptions::from_config(config, config_path, args.jobs)?;
+    let selected = Selection::from_args(args).select(&tests)?;
+
+    let start = std::time::Instant::now();
+    let fail_fast = config.replay.fail_fast;
+    let mut diffs = Vec::new();
+    let mut reports = Vec::new();
+    for ((suite, tests), selected) in suites.iter().zip(&tests).zip(&selected) {
+        if selected.is_empty() {
+            continue;
+        }
+        // Suites after a failure are still reported, their tests as skipped.
+        if !diffs.is_empty() && fail_fast {
+            let suite_tests = selected.iter().map(|&i| &tests[i]);
+            reports.push(SuiteReport::new(suite.name.clone(), suite_tests));
+            continue;
+        }
+        if let Some(name) = &suite.name {
+            println!("Suite: {}", name);
+        }
+        let (suite_diffs, report) =
+            replay_suite(config, base_dir, suite, tests, selected, &options, &painter)?;
+        diffs.extend(suite_diffs);
+        reports.push(report);
     }
+    let failed = !diffs.is_empty();
 
+    // Update config with replay results
+    let elapsed = chrono::Duration::from_std(start.elapsed())?;
+    let result = if failed {
+        ReplayResult::Fail
+    } else {
+        ReplayResult::Pass
+    };
+    config.update_latest_replay(config_path, elapsed, result, diffs)?;
+    if let Some(report) = report {
+        report.write(&reports)?;
+    }
+
+    if failed {
+        anyhow::bail!("Replay failed");
+    }
+
+    println!("All tests passed!");
+    Ok(())
+}
+
+/// Replays the selected tests of a suite against its latest snapshot and returns the
+/// differences along with the report of each test's result.
+fn replay_suite(
+    config: &Config,
+    base_dir: &Path,
+    suite: &Suite,
+    tests: &[TestCase],
+    selected: &[usize],
+    options: &RunOptions,
+    painter: &Painter,
+) -> Result<(Vec<ReplayDiff>, SuiteReport)> {
     // Get latest snapshot
-    let snapshot_filename = match config.get_latest_snapshot() {
-        Some(path) => path,
-        None => anyhow::bail!("No snapshots found. Run `rere record` first."),
+    let snapshot_filename = match (config.get_latest_snapshot(suite), &suite.name) {
+        (Some(path), _) => path,
+        (None, None) => anyhow::bail!("No snapshots found. Run `rere record` first."),
+        (None, Some(name)) => anyhow::bail!(
+            "No snapshots found for suite `{}`. Run `rere record` first.",
+            name
+        ),
     };
     let snapshot_path = base_dir
         .join(&config.common.snapshot_dir)
@@ -35,182 +117,341 @@ pub fn replay(config: &mut Config, config_path: &PathBuf) -> Result<()> {
         );
     };
 
-    // Read test list
-    let shells = load_test_commands(&test_path)?;
+    let snapshot = read_snapshot(&snapshot_path)?;
+    let expected = snapshot.outputs;
 
-    // Read snapshot
-    let mut reader = BiReader::new(File::open(snapshot_path)?);
-    let count_field = reader.read_field_default()?;
-    let count = match count_field {
-        BiField::Integer { value, .. } => value as usize,
-        _ => anyhow::bail!("Expected integer count field"),
-    };
-
-    if count != shells.len() {
+    if expected.len() != tests.len() {
         anyhow::bail!(
             "Number of commands in test file ({}) doesn't match snapshot ({})",
-            shells.len(),
-            count
+            tests.len(),
+            expected.len()
         );
     }
 
-    let start = std::time::Instant::now();
-    let mut failed = false;
+    let fail_fast = config.replay.fail_fast;
     let mut diffs = Vec::new();
+    let mut report = SuiteReport::new(suite.name.clone(), selected.iter().map(|&i| &tests[i]));
+
+    // Snapshots recorded before the interpreter was stored are not compared.
+    if let Some(recorded) = &snapshot.shell {
+        diffs = compare_shell(recorded, &options.shell, fail_fast);
+        for diff in &diffs {
+            print_diff(diff, painter);
+        }
+        if !diffs.is_empty() {
+            report.tests.insert(
+                0,
+                TestReport {
+                    name: "shell".to_owned(),
+                    run: None,
+                    failures: diffs.iter().map(failure).collect(),
+                },
+            );
+        }
+    }
+    // Index of the first test in `report`.
+    let first = report.tests.len() - selected.len();
 
     // Replay each command and compare outputs
-    for shell in shells {
-        println!("Replaying: {}", shell);
-
-        // Read expected output from snapshot
-        let expected_shell = match reader.read_field_default()? {
-            BiField::Blob { data, .. } => String::from_utf8(data)?,
-            _ => anyhow::bail!("Expected blob field for shell command"),
-        };
-
-        if shell != expected_shell {
-            let diff = ReplayDiff {
-                shell: shell.clone(),
-                field: "shell command".to_owned(),
-                expected: DiffContent::Single(expected_shell),
-                actual: DiffContent::Single(shell.clone()),
+    let subset: Vec<_> = match !diffs.is_empty() && fail_fast {
+        true => Vec::new(),
+        false => selected.iter().map(|&i| tests[i].clone()).collect(),
+    };
+    let normalizers = options.normalizers(&subset)?;
+    run_tests(&subset, options, |position, test, raw, elapsed| {
+        println!("Replaying: {}", test.label());
+
+        let output = normalizers[position].normalize(&raw);
+        let expected = &expected[selected[position]];
+        let mut test_diffs =
+            compare_output(expected, test, &output, fail_fast, config.replay.context);
+        // Output diffs keep the raw output when normalization changed it.
+        for diff in &mut test_diffs {
+            let (raw, normalized) = match diff.stream {
+                Some(Stream::Stdout) => (&raw.stdout, &output.stdout),
+                Some(Stream::Stderr) => (&raw.stderr, &output.stderr),
+                None => continue,
             };
-            print_diff(&diff);
-            diffs.push(diff);
-            if config.replay.fail_fast {
-                failed = true;
-                break;
+            if raw != normalized {
+                diff.raw = Some(display_lines(raw));
             }
         }
+        for diff in &test_diffs {
+            print_diff(diff, painter);
+        }
+        let result = &mut report.tests[first + position];
+        result.failures = test_diffs.iter().map(failure).collect();
+        result.run = Some((raw, elapsed));
+        if test_diffs.is_empty() {
+            return Ok(ControlFlow::Continue(()));
+        }
 
-        let expected_returncode = match reader.read_field_default()? {
-            BiField::SignedInteger { value, .. } => value,
-            _ => anyhow::bail!("Expected integer field for return code"),
-        };
+        diffs.extend(test_diffs);
+        Ok(match fail_fast {
+            true => ControlFlow::Break(()),
+            false => ControlFlow::Continue(()),
+        })
+    })?;
 
-        let expected_stdout = match reader.read_field_default()? {
-            BiField::Blob { data, .. } => data,
-            _ => anyhow::bail!("Expected blob field for stdout"),
-        };
+    for diff in &mut diffs {
+        diff.suite = suite.name.clone();
+    }
+    Ok((diffs, report))
+}
 
-        let expected_stderr = match reader.read_field_default()? {
-            BiField::Blob { data, .. } => data,
-            _ => anyhow::bail!("Expected blob field for stderr"),
-        };
+/// Compares the interpreter and wrapper a snapshot was recorded with against the current ones.
+/// With `fail_fast` only the first difference is returned.
+fn compare_shell(
+    recorded: &ShellConfig,
+    current: &ShellConfig,
+    fail_fast: bool,
+) -> Vec<ReplayDiff> {
+    let join = |args: &[String]| match args.is_empty() {
+        true => DiffContent::Single("<none>".to_owned()),
+        false => DiffContent::Single(args.join(" ")),
+    };
+    let mut diffs = Vec::new();
+    if recorded.interpreter != current.interpreter {
+        diffs.push(ReplayDiff {
+            suite: None,
+            shell: String::new(),
+            name: None,
+            field: "interpreter".to_owned(),
+            stream: None,
+            expected: join(&recorded.interpreter),
+            actual: join(&current.interpreter),
+            raw: None,
+            summary: None,
+            hunks: Vec::new(),
+        });
+    }
+    if recorded.wrapper != current.wrapper {
+        diffs.push(ReplayDiff {
+            suite: None,
+            shell: String::new(),
+            name: None,
+            field: "wrapper".to_owned(),
+            stream: None,
+            expected: join(&recorded.wrapper),
+            actual: join(&current.wrapper),
+            raw: None,
+            summary: None,
+            hunks: Vec::new(),
+        });
+    }
 
-        // Capture actual output
-        let output = capture(&shell)?;
+    if fail_fast {
+        diffs.truncate(1);
+    }
+    diffs
+}
 
-        // Compare outputs
-        if output.returncode as i64 != expected_returncode {
-            let diff = ReplayDiff {
-                shell: shell.clone(),
-                field: "return code".to_owned(),
-                expected: DiffContent::Single(expected_returncode.to_string()),
-                actual: DiffContent::Single(output.returncode.to_string()),
-            };
-            print_diff(&diff);
-            diffs.push(diff);
-            if config.replay.fail_fast {
-                failed = true;
-                break;
+/// Compares a command's output against its snapshot entry. Line differences come with a unified
+/// diff keeping `context` unchanged lines around each change. With `fail_fast` only the first
+/// difference is returned.
+fn compare_output(
+    expected: &CommandOutput,
+    test: &TestCase,
+    actual: &CommandOutput,
+    fail_fast: bool,
+    context: usize,
+) -> Vec<ReplayDiff> {
+    let shell = test.shell.as_str();
+    let diff = |field: &str, expected: DiffContent, actual: DiffContent| ReplayDiff {
+        suite: None,
+        shell: shell.to_owned(),
+        name: test.name.clone(),
+        field: field.to_owned(),
+        stream: None,
+        hunks: match (&expected, &actual) {
+            (DiffContent::Lines(expected), DiffContent::Lines(actual)) => {
+                unified_diff(expected, actual, context)
             }
-        }
+            _ => Vec::new(),
+        },
+        expected,
+        actual,
+        raw: None,
+        summary: None,
+    };
 
-        if output.stdout != expected_stdout {
-            let diff = ReplayDiff {
-                shell: shell.clone(),
-                field: "stdout".to_owned(),
-                expected: DiffContent::Lines(
-                    String::from_utf8_lossy(&expected_stdout)
-                        .lines()
-                        .map(|s| s.to_string())
-                        .collect(),
-                ),
-                actual: DiffContent::Lines(
-                    String::from_utf8_lossy(&output.stdout)
-                        .lines()
-                        .map(|s| s.to_string())
-                        .collect(),
-                ),
-            };
-            print_diff(&diff);
-            diffs.push(diff);
-            if config.replay.fail_fast {
-                failed = true;
-                break;
-            }
+    let mut diffs = Vec::new();
+    if expected.shell != shell {
+        diffs.push(diff(
+            "shell command",
+            DiffContent::Single(expected.shell.clone()),
+            DiffContent::Single(shell.to_owned()),
+        ));
+    }
+    if expected.stdin != actual.stdin {
+        let expected = expected.stdin.as_deref().unwrap_or_default();
+        let actual = actual.stdin.as_deref().unwrap_or_default();
+        let (expected_lines, actual_lines) = output_diff_lines(expected, actual);
+        diffs.push(ReplayDiff {
+            summary: binary_summary(expected, actual),
+            ..diff(
+                "stdin",
+                DiffContent::Lines(expected_lines),
+                DiffContent::Lines(actual_lines),
+            )
+        });
+    }
+    // Snapshots recorded before the digest was stored are not compared.
+    if let (Some(recorded), Some(current)) = (expected.env_digest, actual.env_digest) {
+        if recorded != current {
+            diffs.push(diff(
+                "environment digest",
+                DiffContent::Single(format!("{:08x}", recorded)),
+                DiffContent::Single(format!("{:08x}", current)),
+            ));
         }
-
-        if output.stderr != expected_stderr {
-            let diff = ReplayDiff {
-                shell: shell.clone(),
-                field: "stderr".to_owned(),
-                expected: DiffContent::Lines(
-                    String::from_utf8_lossy(&expected_stderr)
-                        .lines()
-                        .map(|s| s.to_string())
-                        .collect(),
-                ),
-                actual: DiffContent::Lines(
-                    String::from_utf8_lossy(&output.stderr)
-                        .lines()
-                        .map(|s| s.to_string())
-                        .collect(),
+    }
+    if expected.timed_out != actual.timed_out {
+        diffs.push(diff(
+            "outcome",
+            DiffContent::Single(describe_outcome(expected)),
+            DiffContent::Single(describe_outcome(actual)),
+        ));
+    } else if let Some(code) = test.expect_exit.filter(|&code| code != actual.returncode) {
+        // Checked whatever `exit_code` is, it only says how to compare against the snapshot.
+        diffs.push(diff(
+            "exit code",
+            DiffContent::Single(code.to_string()),
+            DiffContent::Single(actual.returncode.to_string()),
+        ));
+    } else if test.exit_code == ExitCheck::Exact && expected.returncode != actual.returncode {
+        diffs.push(diff(
+            "return code",
+            DiffContent::Single(expected.returncode.to_string()),
+            DiffContent::Single(actual.returncode.to_string()),
+        ));
+    }
+    let streams = [
+        (
+            Stream::Stdout,
+            &test.stdout,
+            &expected.stdout,
+            &actual.stdout,
+        ),
+        (
+            Stream::Stderr,
+            &test.stderr,
+            &expected.stderr,
+            &actual.stderr,
+        ),
+    ];
+    for (stream, check, expected, actual) in streams {
+        if let Some((mode, expected_lines, actual_lines)) = check_stream(check, expected, actual) {
+            let (field, summary) = match mode {
+                Some((mode, failure)) => (
+                    format!("{} ({})", stream.name(), mode),
+                    Some(format!("{} {}", stream.name(), failure)),
                 ),
+                None => (stream.name().to_owned(), binary_summary(expected, actual)),
             };
-            print_diff(&diff);
-            diffs.push(diff);
-            if config.replay.fail_fast {
-                failed = true;
-                break;
-            }
+            diffs.push(ReplayDiff {
+                summary,
+                stream: Some(stream),
+                ..diff(&field, expected_lines, actual_lines)
+            });
         }
     }
 
-    // Update config with replay results
-    let elapsed = chrono::Duration::from_std(start.elapsed())?;
-    let result = if failed {
-        ReplayResult::Fail
-    } else {
-        ReplayResult::Pass
-    };
-    config.update_latest_replay(config_path, elapsed, result, diffs)?;
+    if fail_fast {
+        diffs.truncate(1);
+    }
+    diffs
+}
 
-    if failed {
-        anyhow::bail!("Replay failed");
+/// A comparison mode and how a stream failed it, e.g. `("regex", "does not match the regex")`.
+type ModeFailure = (&'static str, &'static str);
+
+/// Checks a replayed stream, returning the mode and how the stream failed it (unless exact) and
+/// the expected and actual lines to report when it fails, hexdumps for binary output compared
+/// exactly.
+fn check_stream(
+    check: &OutputCheck,
+    expected: &[u8],
+    actual: &[u8],
+) -> Option<(Option<ModeFailure>, DiffContent, DiffContent)> {
+    let lines = |lines: Vec<String>| DiffContent::Lines(lines);
+    match check {
+        OutputCheck::Exact if expected != actual => {
+            let (expected, actual) = output_diff_lines(expected, actual);
+            Some((None, lines(expected), lines(actual)))
+        }
+        OutputCheck::Unordered => {
+            // Sorted, so the report lines up the lines that differ.
+            let (mut expected, mut actual) = (output_lines(expected), output_lines(actual));
+            expected.sort();
+            actual.sort();
+            let mode = ("unordered", "does not have the snapshot's lines");
+            (expected != actual).then_some((Some(mode), lines(expected), lines(actual)))
+        }
+        // An empty text is contained in any output.
+        OutputCheck::Contains(text)
+            if !text.is_empty()
+                && !actual
+                    .windows(text.len())
+                    .any(|window| window == text.as_bytes()) =>
+        {
+            Some((
+                Some(("contains", "does not contain the expected text")),
+                lines(output_lines(text.as_bytes())),
+                lines(output_lines(actual)),
+            ))
+        }
+        OutputCheck::Regex(pattern) if !pattern.is_match(actual) => Some((
+            Some(("regex", "does not match the regex")),
+            lines(vec![pattern.as_str().to_owned()]),
+            lines(output_lines(actual)),
+        )),
+        _ => None,
     }
+}
 
-    println!("All tests passed!");
-    Ok(())
+fn describe_outcome(output: &CommandOutput) -> String {
+    match output.timed_out {
+        true => "timed out".to_owned(),
+        false => format!("exited with code {}", output.returncode),
+    }
 }
 
-fn print_diff(diff: &ReplayDiff) {
+fn print_diff(diff: &ReplayDiff, painter: &Painter) {
     println!("\nUnexpected {}:", diff.field);
+    for line in diff_lines(diff, painter) {
+        println!("  {}", line);
+    }
+}
+
+/// Lines describing a difference, below its `Unexpected <field>:` heading.
+fn diff_lines(diff: &ReplayDiff, painter: &Painter) -> Vec<String> {
+    let mut lines = Vec::new();
     match (&diff.expected, &diff.actual) {
         (DiffContent::Single(expected), DiffContent::Single(actual)) => {
             if expected != actual {
-                println!("  Expected: {} -> Actual: {}", expected, actual);
+                let (expected, actual) = painter.single(expected, actual);
+                lines.push(format!("Expected: {} -> Actual: {}", expected, actual));
             }
         }
-        (DiffContent::Lines(expected), DiffContent::Lines(actual)) => {
-            println!("  Expected:");
-            let mut actual_iter = actual.iter();
-            for exp_line in expected {
-                if let Some(act_line) = actual_iter.next() {
-                    if exp_line != act_line {
-                        println!("    {} -> {}", exp_line, act_line);
-                    } else {
-                        println!("    {}", exp_line);
-                    }
-                } else {
-                    println!("    {} -> <missing>", exp_line);
-                }
-            }
-            for act_line in actual_iter {
-                println!("    <missing> -> {}", act_line);
+        (DiffContent::Lines(_), DiffContent::Lines(_)) => {
+            lines.extend(diff.summary.clone());
+            if !diff.hunks.is_empty() {
+                lines.extend(painter.file_headers());
+                lines.extend(diff.hunks.iter().flat_map(|hunk| painter.hunk(hunk)));
             }
         }
         _ => unreachable!("Mismatched diff content types"),
     }
+    if let Some(raw) = &diff.raw {
+        lines.push("Actual before normalization:".to_owned());
+        lines.extend(raw.iter().map(|line| format!("  {}", line)));
+    }
+    lines
+}
+
+/// Message and uncolored details of a difference, for reports.
+fn failure(diff: &ReplayDiff) -> (String, String) {
+    let details = diff_lines(diff, &Painter::new(ColorChoice::Never));
+    (format!("Unexpected {}", diff.field), details.join("\n"))
 }
@@ -0,0 +1,274 @@
+use crate::diff::display_lines;
+use crate::shell::CommandOutput;
+use crate::test_list::TestCase;
+use anyhow::{Context, Result};
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Name of the JUnit test suite of the test file when the config declares no suites.
+const DEFAULT_SUITE: &str = "rere";
+
+/// Format of a replay report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// JUnit XML, as read by most CI test views.
+    Junit,
+}
+
+/// Report file a replay writes, see [`parse_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportTarget {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+/// Parses a `<FORMAT>=<PATH>` report, e.g. `junit=report.xml`.
+pub fn parse_report(value: &str) -> Result<ReportTarget, String> {
+    let Some((format, path)) = value.split_once('=') else {
+        return Err(format!("`{}` is not <FORMAT>=<PATH>", value));
+    };
+    let format = match format {
+        "junit" => ReportFormat::Junit,
+        _ => {
+            return Err(format!(
+                "`{}` is not a report format, expected `junit`",
+                format
+            ))
+        }
+    };
+    if path.is_empty() {
+        return Err("the report path is empty".to_owned());
+    }
+    Ok(ReportTarget {
+        format,
+        path: PathBuf::from(path),
+    })
+}
+
+/// Results of the selected tests of a suite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiteReport {
+    /// Name of the suite, `None` for the test file.
+    pub name: Option<String>,
+    pub tests: Vec<TestReport>,
+}
+
+impl SuiteReport {
+    /// Report of `tests`, none of which ran yet.
+    pub fn new<'a>(name: Option<String>, tests: impl IntoIterator<Item = &'a TestCase>) -> Self {
+        let tests = tests
+            .into_iter()
+            .map(|test| TestReport {
+                name: test.label().to_owned(),
+                run: None,
+                failures: Vec::new(),
+            })
+            .collect();
+        Self { name, tests }
+    }
+}
+
+/// Result of a test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestReport {
+    pub name: String,
+    /// Output (before normalization) and run time, `None` when the test didn't run.
+    pub run: Option<(CommandOutput, Duration)>,
+    /// Message and details of each difference found.
+    pub failures: Vec<(String, String)>,
+}
+
+impl TestReport {
+    /// Whether the test neither ran nor failed, i.e. the replay stopped before it.
+    fn skipped(&self) -> bool {
+        self.run.is_none() && self.failures.is_empty()
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.run
+            .as_ref()
+            .map_or(Duration::ZERO, |(_, elapsed)| *elapsed)
+    }
+}
+
+impl ReportTarget {
+    /// Writes the report of the replayed suites, creating its parent directories.
+    pub fn write(&self, suites: &[SuiteReport]) -> Result<()> {
+        let report = match self.format {
+            ReportFormat::Junit => junit(suites),
+        };
+        let write = || {
+            if let Some(parent) = self.path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&self.path, report)
+        };
+        write().with_context(|| format!("Failed to write report to {}", self.path.display()))
+    }
+}
+
+/// JUnit XML report with a `testsuite` per suite and a `testcase` per test.
+fn junit(suites: &[SuiteReport]) -> String {
+    let totals = |tests: &mut dyn Iterator<Item = &TestReport>| {
+        tests.fold(
+            (0, 0, 0, Duration::ZERO),
+            |(count, failed, skipped, time), test| {
+                (
+                    count + 1,
+                    failed + usize::from(!test.failures.is_empty()),
+                    skipped + usize::from(test.skipped()),
+                    time + test.elapsed(),
+                )
+            },
+        )
+    };
+    let attributes = |(count, failed, skipped, time): (usize, usize, usize, Duration)| {
+        format!(
+            "tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\"",
+            count,
+            failed,
+            skipped,
+            time.as_secs_f64()
+        )
+    };
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let all = totals(&mut suites.iter().flat_map(|suite| &suite.tests));
+    writeln!(
+        xml,
+        "<testsuites name=\"{}\" {}>",
+        DEFAULT_SUITE,
+        attributes(all)
+    )
+    .unwrap();
+    for suite in suites {
+        let name = escape(suite.name.as_deref().unwrap_or(DEFAULT_SUITE));
+        let suite_totals = attributes(totals(&mut suite.tests.iter()));
+        writeln!(xml, "  <testsuite name=\"{}\" {}>", name, suite_totals).unwrap();
+        for test in &suite.tests {
+            write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">",
+                escape(&test.name),
+                name,
+                test.elapsed().as_secs_f64()
+            )
+            .unwrap();
+            if test.skipped() {
+                xml += "\n      <skipped message=\"Not run, the replay stopped at an earlier \
+                        failure\"/>";
+            }
+            for (message, details) in &test.failures {
+                write!(
+                    xml,
+                    "\n      <failure message=\"{}\">{}</failure>",
+                    escape(message),
+                    escape(details)
+                )
+                .unwrap();
+            }
+            if let Some((output, _)) = &test.run {
+                let streams = [
+                    ("system-out", &output.stdout),
+                    ("system-err", &output.stderr),
+                ];
+                for (element, bytes) in streams {
+                    if !bytes.is_empty() {
+                        let text = escape(&display_lines(bytes).join("\n"));
+                        write!(xml, "\n      <{0}>{1}</{0}>", element, text).unwrap();
+                    }
+                }
+            }
+            xml += "\n    </testcase>\n";
+        }
+        xml += "  </testsuite>\n";
+    }
+    xml += "</testsuites>\n";
+    xml
+}
+
+/// `text` escaped for XML content and attributes, with the control characters XML can't hold
+/// replaced by `\u{fffd}`.
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_owned(),
+            '<' => "&lt;".to_owned(),
+            '>' => "&gt;".to_owned(),
+            '"' => "&quot;".to_owned(),
+            '\'' => "&apos;".to_owned(),
+            '\t' | '\n' | '\r' => c.to_string(),
+            '\0'..='\x1f' => '\u{fffd}'.to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_report() {
+        assert_eq!(
+            parse_report("junit=out/report.xml").unwrap(),
+            ReportTarget {
+                format: ReportFormat::Junit,
+                path: PathBuf::from("out/report.xml"),
+            }
+        );
+        assert!(parse_report("junit").is_err());
+        assert!(parse_report("junit=").is_err());
+        assert!(parse_report("tap=report.tap").is_err());
+    }
+
+    #[test]
+    fn test_junit() {
+        let output = CommandOutput {
+            shell: "echo '<ok>'".to_owned(),
+            stdin: None,
+            returncode: 0,
+            stdout: b"<ok>\n".to_vec(),
+            stderr: Vec::new(),
+            timed_out: false,
+            env_digest: None,
+        };
+        let suites = [SuiteReport {
+            name: Some("cli".to_owned()),
+            tests: vec![
+                TestReport {
+                    name: "echo '<ok>'".to_owned(),
+                    run: Some((output, Duration::from_millis(1500))),
+                    failures: vec![("Unexpected stdout".to_owned(), "-a & b\x1b".to_owned())],
+                },
+                TestReport {
+                    name: "later".to_owned(),
+                    run: None,
+                    failures: Vec::new(),
+                },
+            ],
+        }];
+        assert_eq!(
+            junit(&suites),
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<testsuites name=\"rere\" tests=\"2\" failures=\"1\" skipped=\"1\" ",
+                "time=\"1.500\">\n",
+                "  <testsuite name=\"cli\" tests=\"2\" failures=\"1\" skipped=\"1\" ",
+                "time=\"1.500\">\n",
+                "    <testcase name=\"echo &apos;&lt;ok&gt;&apos;\" classname=\"cli\" ",
+                "time=\"1.500\">\n",
+                "      <failure message=\"Unexpected stdout\">-a &amp; b\u{fffd}</failure>\n",
+                "      <system-out>&lt;ok&gt;</system-out>\n",
+                "    </testcase>\n",
+                "    <testcase name=\"later\" classname=\"cli\" time=\"0.000\">\n",
+                "      <skipped message=\"Not run, the replay stopped at an earlier ",
+                "failure\"/>\n",
+                "    </testcase>\n",
+                "  </testsuite>\n",
+                "</testsuites>\n",
+            )
+        );
+    }
+}
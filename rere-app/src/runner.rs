@@ -0,0 +1,309 @@
+use crate::config::{Config, EnvConfig, ShellConfig};
+use crate::environment::Environment;
+use crate::normalize::Normalizer;
+use crate::shell::{capture, CommandOutput};
+use crate::test_list::{timeout_from_secs, TestCase};
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Settings shared by every test in a run.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Maximum number of commands running at once, see [`resolve_jobs`].
+    pub jobs: usize,
+    /// Timeout of tests without their own.
+    pub timeout: Option<Duration>,
+    /// Interpreter and wrapper the commands are run with.
+    pub shell: ShellConfig,
+    /// Environment settings, combined with each test's overrides.
+    pub env: EnvConfig,
+    /// Directory commands run from, see [`Config::resolve_workdir`].
+    pub workdir: Option<PathBuf>,
+    /// Rules applied to every test's output, combined with each test's own.
+    pub normalizer: Normalizer,
+}
+
+impl RunOptions {
+    /// Options from the config at `config_path`, with `jobs` given on the command line.
+    pub fn from_config(config: &Config, config_path: &Path, jobs: Option<usize>) -> Result<Self> {
+        let timeout = config
+            .common
+            .timeout
+            .map(timeout_from_secs)
+            .transpose()
+            .context("Invalid `timeout` in config")?;
+        Ok(Self {
+            jobs: jobs.unwrap_or(config.common.jobs),
+            timeout,
+            shell: config.shell.clone(),
+            env: config.env.clone(),
+            workdir: config.resolve_workdir(config_path)?,
+            normalizer: Normalizer::new(&config.normalize)
+                .context("Invalid `normalize` in config")?,
+        })
+    }
+
+    /// Normalizer of each test, compiled once per run. Tests without rules of their own share
+    /// `normalizer`.
+    pub fn normalizers(&self, tests: &[TestCase]) -> Result<Vec<Cow<'_, Normalizer>>> {
+        tests
+            .iter()
+            .map(|test| {
+                let rules = &test.normalize;
+                Ok(match rules.builtin.is_empty() && rules.rule.is_empty() {
+                    true => Cow::Borrowed(&self.normalizer),
+                    false => Cow::Owned(self.normalizer.extend(rules)?),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Number of worker threads for a `jobs` setting, `0` meaning one per available CPU.
+pub fn resolve_jobs(jobs: usize) -> usize {
+    match jobs {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        jobs => jobs,
+    }
+}
+
+/// Directory a test runs from: its `cd` relative to the configured working directory.
+fn test_workdir(test: &TestCase, options: &RunOptions) -> Option<PathBuf> {
+    match (&options.workdir, &test.cd) {
+        (Some(workdir), Some(cd)) => Some(workdir.join(cd)),
+        (workdir, cd) => cd.as_ref().or(workdir.as_ref()).cloned(),
+    }
+}
+
+/// Runs the tests on up to `options.jobs` threads and hands each output to `on_output` in test
+/// file order, regardless of the order the commands finish in.
+///
+/// Serial tests never run concurrently with any other test. Once `on_output` returns
+/// `ControlFlow::Break` (or an error) no further tests are started and the remaining outputs
+/// are discarded.
+///
+/// ### Parameters
+/// - `tests`: Tests to run.
+/// - `options`: Concurrency and defaults for the tests.
+/// - `on_output`: Called with the index, test, output, and run time of each test in order.
+pub fn run_tests<F>(tests: &[TestCase], options: &RunOptions, mut on_output: F) -> Result<()>
+where
+    F: FnMut(usize, &TestCase, CommandOutput, Duration) -> Result<ControlFlow<()>>,
+{
+    let workers = resolve_jobs(options.jobs).clamp(1, tests.len().max(1));
+    let next = &AtomicUsize::new(0);
+    let stop = &AtomicBool::new(false);
+    // Parallel tests hold the lock shared, serial tests exclusively.
+    let serial = &RwLock::new(());
+
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..workers {
+            let sender = sender.clone();
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(test) = tests.get(index) else {
+                        break;
+                    };
+                    let timeout = test.timeout.or(options.timeout);
+                    let env = Environment::resolve(&options.env, &test.env);
+                    let workdir = test_workdir(test, options);
+                    let run = || {
+                        capture(
+                            &test.shell,
+                            &options.shell,
+                            &env,
+                            workdir.as_deref(),
+                            test.stdin.as_deref(),
+                            timeout,
+                        )
+                    };
+                    let timed = || {
+                        let start = Instant::now();
+                        run().map(|output| (output, start.elapsed()))
+                    };
+                    let output = if test.serial {
+                        let _guard = serial.write().unwrap_or_else(|e| e.into_inner());
+                        timed()
+                    } else {
+                        let _guard = serial.read().unwrap_or_else(|e| e.into_inner());
+                        timed()
+                    };
+                    if sender.send((index, output)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Outputs that finished ahead of an earlier test.
+        let mut pending = BTreeMap::new();
+        let mut expected = 0;
+        for (index, output) in receiver {
+            pending.insert(index, output);
+            while let Some(output) = pending.remove(&expected) {
+                let flow = output.and_then(|(output, elapsed)| {
+                    on_output(expected, &tests[expected], output, elapsed)
+                });
+                expected += 1;
+                match flow {
+                    Ok(ControlFlow::Continue(())) => {}
+                    Ok(ControlFlow::Break(())) => {
+                        stop.store(true, Ordering::Relaxed);
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        stop.store(true, Ordering::Relaxed);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BuiltinRule, NormalizeConfig, NormalizeRule};
+
+    fn test_case(shell: &str, serial: bool) -> TestCase {
+        TestCase {
+            shell: shell.to_owned(),
+            serial,
+            ..Default::default()
+        }
+    }
+
+    fn options(jobs: usize) -> RunOptions {
+        RunOptions {
+            jobs,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_normalizers() {
+        let mut options = options(1);
+        options.normalizer = Normalizer::new(&NormalizeConfig {
+            builtin: vec![BuiltinRule::Uuid],
+            rule: Vec::new(),
+        })
+        .unwrap();
+        let mut custom = test_case("echo", false);
+        custom.normalize.rule.push(NormalizeRule {
+            pattern: "[0-9]+".to_owned(),
+            replacement: "N".to_owned(),
+        });
+        let tests = [test_case("echo", false), custom];
+
+        let normalizers = options.normalizers(&tests).unwrap();
+        assert!(matches!(normalizers[0], Cow::Borrowed(_)));
+        assert!(matches!(normalizers[1], Cow::Owned(_)));
+
+        let output = CommandOutput {
+            shell: "echo".to_owned(),
+            stdin: None,
+            returncode: 0,
+            stdout: b"took 12ms\n".to_vec(),
+            stderr: Vec::new(),
+            timed_out: false,
+            env_digest: None,
+        };
+        assert!(matches!(
+            normalizers[0].normalize(&output),
+            Cow::Borrowed(_)
+        ));
+        let normalized = normalizers[1].normalize(&output);
+        assert_eq!(normalized.stdout, b"took Nms\n");
+        assert_eq!(normalized.shell, output.shell);
+    }
+
+    #[test]
+    fn test_outputs_in_order() {
+        let tests = vec![
+            test_case("sleep 0.3; echo 0", false),
+            test_case("echo 1", false),
+            test_case("sleep 0.1; echo 2", true),
+            test_case("echo 3", false),
+        ];
+        let mut seen = Vec::new();
+        run_tests(&tests, &options(4), |index, test, output, _| {
+            assert_eq!(test.shell, tests[index].shell);
+            seen.push(String::from_utf8(output.stdout).unwrap());
+            Ok(ControlFlow::Continue(()))
+        })
+        .unwrap();
+        assert_eq!(seen, vec!["0\n", "1\n", "2\n", "3\n"]);
+    }
+
+    #[test]
+    fn test_serial_runs_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        // Each command logs its start and end, with time in between for others to interleave.
+        let logged = |i: usize, serial: bool| {
+            let shell = format!(
+                "echo start {0} >> '{1}'; sleep 0.2; echo end {0} >> '{1}'",
+                i,
+                log.display()
+            );
+            test_case(&shell, serial)
+        };
+        let tests = vec![
+            logged(0, false),
+            logged(1, false),
+            logged(2, true),
+            logged(3, false),
+            logged(4, false),
+        ];
+        run_tests(&tests, &options(4), |_, _, _, _| {
+            Ok(ControlFlow::Continue(()))
+        })
+        .unwrap();
+
+        let log = std::fs::read_to_string(&log).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 10, "{}", log);
+        let start = lines.iter().position(|line| *line == "start 2").unwrap();
+        // Nothing runs while the serial command does: every command that started before it has
+        // ended, and its end is the next line.
+        let running = lines[..start]
+            .iter()
+            .filter(|line| line.starts_with("start"))
+            .count()
+            - lines[..start]
+                .iter()
+                .filter(|line| line.starts_with("end"))
+                .count();
+        assert_eq!(running, 0, "{}", log);
+        assert_eq!(lines[start + 1], "end 2", "{}", log);
+    }
+
+    #[test]
+    fn test_break_stops() {
+        let tests: Vec<TestCase> = (0..20)
+            .map(|i| test_case(&format!("echo {}", i), false))
+            .collect();
+        let mut seen = 0;
+        run_tests(&tests, &options(2), |index, _, _, _| {
+            seen += 1;
+            Ok(match index {
+                3 => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            })
+        })
+        .unwrap();
+        assert_eq!(seen, 4);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::cli::RunArgs;
+use crate::test_list::TestCase;
+use anyhow::Result;
+use regex::Regex;
+use std::ops::RangeInclusive;
+
+/// 1-based command positions given with `--index`, e.g. `3,7-9`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexRanges(Vec<RangeInclusive<usize>>);
+
+impl IndexRanges {
+    fn contains(&self, position: usize) -> bool {
+        self.0.iter().any(|range| range.contains(&position))
+    }
+
+    fn max(&self) -> usize {
+        self.0.iter().map(|range| *range.end()).max().unwrap_or(0)
+    }
+}
+
+/// Parses a comma separated list of 1-based positions and inclusive ranges.
+pub fn parse_index_ranges(list: &str) -> Result<IndexRanges, String> {
+    let parse = |position: &str| match position.trim().parse::<usize>() {
+        Ok(0) => Err("positions start at 1".to_owned()),
+        Ok(position) => Ok(position),
+        Err(_) => Err(format!("`{}` is not a position", position.trim())),
+    };
+
+    list.split(',')
+        .map(|part| match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                match start <= end {
+                    true => Ok(start..=end),
+                    false => Err(format!("`{}` is an empty range", part.trim())),
+                }
+            }
+            None => parse(part).map(|position| position..=position),
+        })
+        .collect::<Result<_, _>>()
+        .map(IndexRanges)
+}
+
+/// Which commands of the test file a record or replay runs.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// Only commands matching this pattern.
+    pub filter: Option<Regex>,
+    /// Only commands at these positions.
+    pub index: Option<IndexRanges>,
+    /// No commands matching this pattern.
+    pub exclude: Option<Regex>,
+    /// Only commands with one of these tags, any command when empty.
+    pub tags: Vec<String>,
+}
+
+impl Selection {
+    /// Selection from the record or replay arguments.
+    pub fn from_args(args: &RunArgs) -> Self {
+        Self {
+            filter: args.filter.clone(),
+            index: args.index.clone(),
+            exclude: args.exclude.clone(),
+            tags: args.tag.clone(),
+        }
+    }
+
+    /// Whether every command is selected regardless of the test file.
+    pub fn is_all(&self) -> bool {
+        self.filter.is_none()
+            && self.index.is_none()
+            && self.exclude.is_none()
+            && self.tags.is_empty()
+    }
+
+    /// Indices of the selected tests of each suite, all of `--filter`, `--index`, `--exclude`,
+    /// and `--tag` have to agree for a test to be selected. Patterns match a test's command or its
+    /// name, positions count from the start of each suite's test file.
+    pub fn select(&self, suites: &[Vec<TestCase>]) -> Result<Vec<Vec<usize>>> {
+        let longest = suites.iter().map(Vec::len).max().unwrap_or(0);
+        if let Some(index) = &self.index {
+            if index.max() > longest {
+                anyhow::bail!(
+                    "Index {} is out of range, {} has {} commands",
+                    index.max(),
+                    match suites.len() {
+                        1 => "the test file",
+                        _ => "the longest test file",
+                    },
+                    longest
+                );
+            }
+        }
+
+        let selected: Vec<Vec<usize>> = suites
+            .iter()
+            .map(|tests| {
+                tests
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, test)| self.matches(i + 1, test))
+                    .map(|(i, _)| i)
+                    .collect()
+            })
+            .collect();
+
+        if selected.iter().all(Vec::is_empty) {
+            anyhow::bail!("No commands match the selection");
+        }
+        Ok(selected)
+    }
+
+    /// Whether the test at the 1-based `position` is selected.
+    fn matches(&self, position: usize, test: &TestCase) -> bool {
+        let matches = |re: &Regex| {
+            re.is_match(&test.shell) || test.name.as_ref().is_some_and(|n| re.is_match(n))
+        };
+        let indexed = self
+            .index
+            .as_ref()
+            .is_none_or(|index| index.contains(position));
+        let matched = self.filter.as_ref().is_none_or(matches);
+        let excluded = self.exclude.as_ref().is_some_and(matches);
+        let tagged = self.tags.is_empty() || test.tags.iter().any(|tag| self.tags.contains(tag));
+        indexed && matched && !excluded && tagged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tests(commands: &[&str]) -> Vec<TestCase> {
+        commands
+            .iter()
+            .map(|shell| TestCase {
+                shell: shell.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_index_ranges() {
+        let ranges = parse_index_ranges("3, 7-9").unwrap();
+        assert_eq!(ranges, IndexRanges(vec![3..=3, 7..=9]));
+        assert!(parse_index_ranges("0").is_err());
+        assert!(parse_index_ranges("5-2").is_err());
+        assert!(parse_index_ranges("a").is_err());
+        assert!(parse_index_ranges("1,").is_err());
+    }
+
+    /// Selection of a single suite.
+    fn select(selection: &Selection, tests: &[TestCase]) -> Result<Vec<usize>> {
+        Ok(selection.select(&[tests.to_vec()])?.remove(0))
+    }
+
+    #[test]
+    fn test_select() {
+        let tests = tests(&["echo a", "echo b", "ls", "echo c", "cargo test"]);
+        assert_eq!(
+            select(&Selection::default(), &tests).unwrap(),
+            [0, 1, 2, 3, 4]
+        );
+
+        let selection = Selection {
+            filter: Some(Regex::new("^echo").unwrap()),
+            index: Some(parse_index_ranges("2-5").unwrap()),
+            exclude: Some(Regex::new("c$").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(select(&selection, &tests).unwrap(), [1]);
+
+        let out_of_range = Selection {
+            index: Some(parse_index_ranges("6").unwrap()),
+            ..Default::default()
+        };
+        assert!(select(&out_of_range, &tests).is_err());
+
+        let nothing = Selection {
+            filter: Some(Regex::new("missing").unwrap()),
+            ..Default::default()
+        };
+        assert!(select(&nothing, &tests).is_err());
+
+        let mut tests = tests;
+        tests[2].name = Some("listing".to_owned());
+        tests[4].tags = vec!["slow".to_owned()];
+        let named = Selection {
+            filter: Some(Regex::new("^list").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(select(&named, &tests).unwrap(), [2]);
+        let tagged = Selection {
+            tags: vec!["fast".to_owned(), "slow".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(select(&tagged, &tests).unwrap(), [4]);
+    }
+
+    #[test]
+    fn test_select_suites() {
+        let suites = [tests(&["echo a", "ls"]), tests(&["echo b", "echo c", "ls"])];
+        let selection = Selection {
+            index: Some(parse_index_ranges("3").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(selection.select(&suites).unwrap(), [vec![], vec![2]]);
+
+        let out_of_range = Selection {
+            index: Some(parse_index_ranges("4").unwrap()),
+            ..Default::default()
+        };
+        let error = out_of_range.select(&suites).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Index 4 is out of range, the longest test file has 3 commands"
+        );
+    }
+}
@@ -1,22 +1,393 @@
-use anyhow::Result;
-use std::process::Command;
+use crate::config::ShellConfig;
+use crate::environment::Environment;
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
 
+/// How often a command with a timeout is polled for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CommandOutput {
     pub shell: String,
+    /// Input the command was fed, `None` for `/dev/null`.
+    pub stdin: Option<Vec<u8>>,
     pub returncode: i32,
     pub stdout: Vec<u8>,
     pub stderr: Vec<u8>,
+    /// Whether the command was killed for running past its timeout.
+    pub timed_out: bool,
+    /// Digest of the environment the command ran with, see [`Environment::digest`]. `None` for
+    /// snapshots recorded before it was stored.
+    pub env_digest: Option<u32>,
 }
 
-pub fn capture(shell: &str) -> Result<CommandOutput> {
-    println!("Capturing: {}", shell);
+/// Runs `shell` with the configured interpreter and wrapper in `env` and captures its output,
+/// from `workdir` when given or the current directory otherwise. The command reads `stdin`, or
+/// `/dev/null` when it's `None`. When `timeout` expires the
+/// command's whole process group is killed and the output up to that point is returned with
+/// `timed_out` set. The timeout also covers processes the command leaves running in the
+/// background that still hold its output open, e.g. `server & echo started`.
+pub fn capture(
+    shell: &str,
+    config: &ShellConfig,
+    env: &Environment,
+    workdir: Option<&Path>,
+    stdin: Option<&[u8]>,
+    timeout: Option<Duration>,
+) -> Result<CommandOutput> {
+    let argv = command_line(shell, config)?;
+    let mut command = Command::new(&argv[0]);
+    command
+        .args(&argv[1..])
+        .stdin(match stdin {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    env.apply(&mut command);
+    if let Some(workdir) = workdir {
+        if !workdir.is_dir() {
+            anyhow::bail!("Working directory not found at: {}", workdir.display());
+        }
+        command.current_dir(workdir);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Own process group, so everything the command spawns can be killed with it.
+        command.process_group(0);
+    }
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to run `{}`", argv[0]))?;
+
+    let writer = write_pipe(child.stdin.take(), stdin);
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
 
-    let output = Command::new("sh").arg("-c").arg(shell).output()?;
+    // A timeout too large to represent is no timeout.
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    let (status, mut timed_out) = match deadline {
+        Some(deadline) => wait_deadline(&mut child, deadline)?,
+        None => (child.wait()?, false),
+    };
+    if let Some(deadline) = deadline.filter(|_| !timed_out) {
+        // Background processes the command left behind may still hold the pipes open.
+        let finished = || {
+            [
+                writer.as_ref().map(|handle| handle.is_finished()),
+                stdout.as_ref().map(|handle| handle.is_finished()),
+                stderr.as_ref().map(|handle| handle.is_finished()),
+            ]
+            .into_iter()
+            .all(|finished| finished.unwrap_or(true))
+        };
+        if !wait_pipes(finished, deadline) {
+            kill_group(&mut child)?;
+            timed_out = true;
+        }
+    }
 
+    join_pipe(writer)?;
     Ok(CommandOutput {
         shell: shell.to_owned(),
-        returncode: output.status.code().unwrap_or(-1),
-        stdout: output.stdout.clone(),
-        stderr: output.stderr.clone(),
+        stdin: stdin.map(<[u8]>::to_vec),
+        returncode: status.code().unwrap_or(-1),
+        stdout: join_pipe(stdout)?,
+        stderr: join_pipe(stderr)?,
+        timed_out,
+        env_digest: Some(env.digest()),
     })
 }
+
+/// Arguments `shell` is run with: the wrapper, then the interpreter followed by `shell`, or
+/// `shell` split like a shell would when the interpreter is empty.
+fn command_line(shell: &str, config: &ShellConfig) -> Result<Vec<String>> {
+    let mut argv = config.wrapper.clone();
+    if config.interpreter.is_empty() {
+        let args = shlex::split(shell)
+            .with_context(|| format!("Cannot split `{}` into arguments", shell))?;
+        argv.extend(args);
+    } else {
+        argv.extend(config.interpreter.iter().cloned());
+        argv.push(shell.to_owned());
+    }
+    if argv.is_empty() {
+        anyhow::bail!("Empty command");
+    }
+    Ok(argv)
+}
+
+/// Waits for `child` to exit, killing its process group once `deadline` has passed.
+fn wait_deadline(child: &mut Child, deadline: Instant) -> Result<(std::process::ExitStatus, bool)> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status, false));
+        }
+        if Instant::now() >= deadline {
+            kill_group(child)?;
+            return Ok((child.wait()?, true));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Waits until `finished` returns `true`, or returns `false` once `deadline` has passed.
+fn wait_pipes(finished: impl Fn() -> bool, deadline: Instant) -> bool {
+    loop {
+        if finished() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(unix)]
+fn kill_group(child: &mut Child) -> Result<()> {
+    // The child leads its own process group, so its pid is the group id.
+    let group = child.id() as libc::pid_t;
+    // SAFETY: `kill` has no memory safety requirements.
+    if unsafe { libc::kill(-group, libc::SIGKILL) } != 0 {
+        let error = std::io::Error::last_os_error();
+        // The group may already be gone.
+        if error.raw_os_error() != Some(libc::ESRCH) {
+            return Err(error.into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn kill_group(child: &mut Child) -> Result<()> {
+    child.kill()?;
+    Ok(())
+}
+
+type PipeThread<T> = Option<thread::JoinHandle<std::io::Result<T>>>;
+type PipeReader = PipeThread<Vec<u8>>;
+type PipeWriter = PipeThread<()>;
+
+/// Reads a pipe to the end on its own thread, so neither pipe can fill up and block the command.
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> PipeReader {
+    pipe.map(|mut pipe| {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            pipe.read_to_end(&mut buf)?;
+            Ok(buf)
+        })
+    })
+}
+
+/// Writes `input` to the command's stdin on its own thread and closes it. A command that exits
+/// without reading all of its input isn't an error.
+fn write_pipe<W: Write + Send + 'static>(pipe: Option<W>, input: Option<&[u8]>) -> PipeWriter {
+    let (mut pipe, input) = (pipe?, input?.to_vec());
+    Some(thread::spawn(move || match pipe.write_all(&input) {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }))
+}
+
+fn join_pipe<T: Default>(reader: PipeThread<T>) -> Result<T> {
+    match reader {
+        Some(handle) => match handle.join() {
+            Ok(result) => Ok(result?),
+            Err(_) => anyhow::bail!("Pipe reader thread panicked"),
+        },
+        None => Ok(T::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell_config(interpreter: &[&str], wrapper: &[&str]) -> ShellConfig {
+        ShellConfig {
+            interpreter: interpreter.iter().map(|arg| arg.to_string()).collect(),
+            wrapper: wrapper.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_capture() {
+        let config = ShellConfig::default();
+        let output = capture(
+            "echo out; echo err >&2; exit 3",
+            &config,
+            &Environment::default(),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+        assert_eq!(output.returncode, 3);
+        assert!(!output.timed_out);
+    }
+
+    #[test]
+    fn test_timeout_kills_group() {
+        let start = Instant::now();
+        // The background sleep holds stdout open, so this only returns if the group is killed.
+        let output = capture(
+            "echo started; sleep 30 & sleep 30",
+            &ShellConfig::default(),
+            &Environment::default(),
+            None,
+            None,
+            Some(Duration::from_millis(200)),
+        )
+        .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(output.timed_out);
+        assert_eq!(output.stdout, b"started\n");
+        assert_eq!(output.returncode, -1);
+    }
+
+    #[test]
+    fn test_timeout_kills_background() {
+        let start = Instant::now();
+        // The shell exits at once, but the background sleep keeps stdout open.
+        let output = capture(
+            "sleep 30 & echo hi",
+            &ShellConfig::default(),
+            &Environment::default(),
+            None,
+            None,
+            Some(Duration::from_secs(1)),
+        )
+        .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(output.timed_out);
+        assert_eq!(output.stdout, b"hi\n");
+
+        // Background output written before the deadline is kept.
+        let output = capture(
+            "(sleep 0.1; echo late) & echo now",
+            &ShellConfig::default(),
+            &Environment::default(),
+            None,
+            None,
+            Some(Duration::from_secs(10)),
+        )
+        .unwrap();
+        assert!(!output.timed_out);
+        assert_eq!(output.stdout, b"now\nlate\n");
+
+        // A timeout too large for a deadline doesn't overflow.
+        let output = capture(
+            "echo ok",
+            &ShellConfig::default(),
+            &Environment::default(),
+            None,
+            None,
+            Some(Duration::MAX),
+        )
+        .unwrap();
+        assert_eq!(output.stdout, b"ok\n");
+    }
+
+    #[test]
+    fn test_command_line() {
+        let wrapped = shell_config(&["bash", "-c"], &["env", "-i"]);
+        assert_eq!(
+            command_line("echo $HOME", &wrapped).unwrap(),
+            ["env", "-i", "bash", "-c", "echo $HOME"]
+        );
+
+        let direct = shell_config(&[], &[]);
+        assert_eq!(
+            command_line("printf '%s\\n' 'a b'", &direct).unwrap(),
+            ["printf", "%s\\n", "a b"]
+        );
+        assert!(command_line("echo 'unterminated", &direct).is_err());
+        assert!(command_line("  ", &direct).is_err());
+    }
+
+    #[test]
+    fn test_capture_wrapper_and_direct() {
+        let wrapped = shell_config(&["sh", "-c"], &["env", "GREETING=hi"]);
+        let output = capture(
+            "echo $GREETING",
+            &wrapped,
+            &Environment::default(),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(output.stdout, b"hi\n");
+
+        // Without an interpreter `$GREETING` reaches echo verbatim.
+        let direct = shell_config(&[], &["env", "GREETING=hi"]);
+        let output = capture(
+            "echo '$GREETING'",
+            &direct,
+            &Environment::default(),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(output.stdout, b"$GREETING\n");
+    }
+
+    #[test]
+    fn test_capture_cleared_env() {
+        let mut env = Environment {
+            clear: true,
+            ..Default::default()
+        };
+        env.vars.insert("ONLY".to_owned(), "1".to_owned());
+        let output = capture("env", &ShellConfig::default(), &env, None, None, None).unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("ONLY=1\n"));
+        assert!(!stdout.contains("HOME="));
+        assert_eq!(output.env_digest, Some(env.digest()));
+    }
+
+    #[test]
+    fn test_capture_workdir() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ShellConfig::default();
+        let env = Environment::default();
+        let output = capture("pwd", &config, &env, Some(dir.path()), None, None).unwrap();
+        let expected = dir.path().canonicalize().unwrap();
+        assert_eq!(
+            output.stdout,
+            format!("{}\n", expected.display()).into_bytes()
+        );
+
+        let missing = dir.path().join("missing");
+        assert!(capture("pwd", &config, &env, Some(&missing), None, None).is_err());
+    }
+
+    #[test]
+    fn test_capture_stdin() {
+        let config = ShellConfig::default();
+        let env = Environment::default();
+        let output = capture("tr a-z A-Z", &config, &env, None, Some(b"hello\n"), None).unwrap();
+        assert_eq!(output.stdout, b"HELLO\n");
+        assert_eq!(output.stdin.as_deref(), Some(&b"hello\n"[..]));
+
+        // Input the command never reads doesn't fail the capture.
+        let input = vec![b'x'; 1 << 20];
+        let output = capture("true", &config, &env, None, Some(&input), None).unwrap();
+        assert_eq!(output.returncode, 0);
+
+        // Without stdin the command reads /dev/null instead of waiting for input.
+        let output = capture("cat", &config, &env, None, None, None).unwrap();
+        assert_eq!(output.stdout, b"");
+        assert_eq!(output.stdin, None);
+    }
+}
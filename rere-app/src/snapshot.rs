@@ -0,0 +1,284 @@
+use crate::config::ShellConfig;
+#[cfg(feature = "compression")]
+use crate::constants::COMPRESS_THRESHOLD;
+use crate::shell::CommandOutput;
+use anyhow::{Context, Result};
+use bi_parser::prelude::*;
+use std::fs::File;
+use std::path::Path;
+
+/// A recorded snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Interpreter and wrapper the commands were recorded with, `None` for snapshots recorded
+    /// before they were stored.
+    pub shell: Option<ShellConfig>,
+    pub outputs: Vec<CommandOutput>,
+}
+
+/// Writes the outputs as a snapshot: a `count` field, the `interpreter` and `wrapper` the
+/// commands ran with, then `shell`, `returncode`, `env_digest`, `stdout`, and `stderr` fields per
+/// test (plus `stdin` for tests fed input and `timed_out` for tests that timed out), and a
+/// trailing checksum.
+pub fn write_snapshot(path: &Path, snapshot: &Snapshot, compress: bool) -> Result<()> {
+    #[cfg(not(feature = "compression"))]
+    if compress {
+        anyhow::bail!("`record.compress` needs rere built with the `compression` feature");
+    }
+    let outputs = &snapshot.outputs;
+    let file = File::create(path)?;
+    let mut writer = BiWriter::new(file).with_checksum(true);
+    #[cfg(feature = "compression")]
+    if compress {
+        writer = writer.with_compression(COMPRESS_THRESHOLD);
+    }
+
+    // Write count of tests
+    writer.write_field_default(&BiField::Integer {
+        name: b"count".to_vec(),
+        value: outputs.len() as u64,
+    })?;
+
+    if let Some(shell) = &snapshot.shell {
+        for (name, args) in [
+            ("interpreter", &shell.interpreter),
+            ("wrapper", &shell.wrapper),
+        ] {
+            let joined = shlex::try_join(args.iter().map(String::as_str))
+                .with_context(|| format!("Cannot store the {} in the snapshot", name))?;
+            writer.write_field_default(&BiField::Blob {
+                name: name.as_bytes().to_vec(),
+                data: joined.into_bytes(),
+            })?;
+        }
+    }
+
+    // Write each test output
+    for output in outputs {
+        writer.write_field_default(&BiField::Blob {
+            name: b"shell".to_vec(),
+            data: output.shell.as_bytes().to_vec(),
+        })?;
+        if let Some(stdin) = &output.stdin {
+            writer.write_field_default(&BiField::Blob {
+                name: b"stdin".to_vec(),
+                data: stdin.clone(),
+            })?;
+        }
+        writer.write_field_default(&BiField::SignedInteger {
+            name: b"returncode".to_vec(),
+            value: output.returncode as i64,
+        })?;
+        if let Some(digest) = output.env_digest {
+            writer.write_field_default(&BiField::Integer {
+                name: b"env_digest".to_vec(),
+                value: digest as u64,
+            })?;
+        }
+        if output.timed_out {
+            writer.write_field_default(&BiField::Integer {
+                name: b"timed_out".to_vec(),
+                value: 1,
+            })?;
+        }
+        writer.write_field_default(&BiField::Blob {
+            name: b"stdout".to_vec(),
+            data: output.stdout.clone(),
+        })?;
+        writer.write_field_default(&BiField::Blob {
+            name: b"stderr".to_vec(),
+            data: output.stderr.clone(),
+        })?;
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+/// Reads a snapshot, verifying its checksum (if it has one) and decompressing blobs. Snapshots
+/// recorded before checksums were added don't have one and are read as is.
+///
+/// Each test entry starts at a `shell` field, the fields after it are matched by name so fields
+/// this version doesn't know about are skipped.
+pub fn read_snapshot(path: &Path) -> Result<Snapshot> {
+    let mut reader = BiReader::new(File::open(path)?).with_checksum(ChecksumPolicy::Verify);
+
+    let corrupted = |e: BiError| {
+        anyhow::anyhow!(
+            "Snapshot at {} is corrupted: {}. Re-record it with `rere record`.",
+            path.display(),
+            e
+        )
+    };
+
+    let count = match reader.read_field_default().map_err(corrupted)? {
+        BiField::Integer { name, value } if name == b"count" => value as usize,
+        _ => anyhow::bail!("Expected integer count field"),
+    };
+
+    let mut shell: Option<ShellConfig> = None;
+    let mut entries: Vec<CommandOutput> = Vec::with_capacity(count);
+    while !reader.is_eof().map_err(corrupted)? {
+        let field = reader.read_field_default().map_err(corrupted)?;
+        if field.name() == b"shell" {
+            let BiField::Blob { data, .. } = field else {
+                anyhow::bail!("Expected blob field for shell command");
+            };
+            entries.push(CommandOutput {
+                shell: String::from_utf8(data)?,
+                stdin: None,
+                returncode: 0,
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+                timed_out: false,
+                env_digest: None,
+            });
+            continue;
+        }
+
+        let Some(entry) = entries.last_mut() else {
+            read_header_field(field, &mut shell)?;
+            continue;
+        };
+        match field {
+            BiField::SignedInteger { name, value } if name == b"returncode" => {
+                entry.returncode = value as i32
+            }
+            BiField::Blob { name, data } if name == b"stdin" => entry.stdin = Some(data),
+            BiField::Blob { name, data } if name == b"stdout" => entry.stdout = data,
+            BiField::Blob { name, data } if name == b"stderr" => entry.stderr = data,
+            BiField::Integer { name, value } if name == b"timed_out" => {
+                entry.timed_out = value != 0
+            }
+            BiField::Integer { name, value } if name == b"env_digest" => {
+                entry.env_digest = Some(value as u32)
+            }
+            field
+                if matches!(
+                    field.name(),
+                    b"returncode" | b"stdin" | b"stdout" | b"stderr" | b"timed_out" | b"env_digest"
+                ) =>
+            {
+                anyhow::bail!(
+                    "Unexpected field type for {}",
+                    String::from_utf8_lossy(field.name())
+                )
+            }
+            _ => {}
+        }
+    }
+
+    if entries.len() != count {
+        anyhow::bail!(
+            "Snapshot count ({}) doesn't match the number of entries ({})",
+            count,
+            entries.len()
+        );
+    }
+
+    Ok(Snapshot {
+        shell,
+        outputs: entries,
+    })
+}
+
+/// Reads a field preceding the first test entry into the recorded interpreter and wrapper.
+fn read_header_field(field: BiField, shell: &mut Option<ShellConfig>) -> Result<()> {
+    let BiField::Blob { name, data } = field else {
+        return Ok(());
+    };
+    if !matches!(name.as_slice(), b"interpreter" | b"wrapper") {
+        return Ok(());
+    }
+
+    let args = String::from_utf8(data)
+        .ok()
+        .and_then(|text| shlex::split(&text))
+        .with_context(|| format!("Invalid {} in snapshot", String::from_utf8_lossy(&name)))?;
+    let shell = shell.get_or_insert_with(|| ShellConfig {
+        interpreter: Vec::new(),
+        wrapper: Vec::new(),
+    });
+    match name.as_slice() {
+        b"interpreter" => shell.interpreter = args,
+        _ => shell.wrapper = args,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.list.bi");
+        let outputs = vec![
+            CommandOutput {
+                shell: "echo a".to_owned(),
+                stdin: None,
+                returncode: 0,
+                stdout: b"a\n".to_vec(),
+                stderr: Vec::new(),
+                timed_out: false,
+                env_digest: Some(42),
+            },
+            CommandOutput {
+                shell: "false".to_owned(),
+                stdin: Some(b"input\n".to_vec()),
+                returncode: 1,
+                stdout: Vec::new(),
+                stderr: b"x".repeat(4096),
+                timed_out: false,
+                env_digest: None,
+            },
+            CommandOutput {
+                shell: "sleep 60".to_owned(),
+                stdin: Some(Vec::new()),
+                returncode: -1,
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+                timed_out: true,
+                env_digest: Some(u32::MAX),
+            },
+        ];
+
+        let snapshot = Snapshot {
+            shell: Some(ShellConfig {
+                interpreter: vec!["bash".to_owned(), "-c".to_owned()],
+                wrapper: vec!["env".to_owned(), "A=x y".to_owned()],
+            }),
+            outputs,
+        };
+
+        let compress = cfg!(feature = "compression");
+        write_snapshot(&path, &snapshot, compress).unwrap();
+        assert_eq!(read_snapshot(&path).unwrap(), snapshot);
+        if !compress {
+            let error = write_snapshot(&path, &snapshot, true).unwrap_err();
+            assert!(error.to_string().contains("`compression` feature"));
+        }
+    }
+
+    #[test]
+    fn test_unknown_fields_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.list.bi");
+        let mut content = b":i count 1\n:b shell 4\ntrue\n:i extra 7\n:s returncode 0\n\
+             :b stdout 0\n\n:b stderr 0\n\n"
+            .to_vec();
+        // Legacy snapshot without a checksum.
+        std::fs::write(&path, &content).unwrap();
+        assert_eq!(read_snapshot(&path).unwrap().outputs.len(), 1);
+
+        let mut crc = Crc32::new();
+        crc.update(&content);
+        content.extend(format!(":i bi.crc32 {}\n", crc.value()).bytes());
+        std::fs::write(&path, &content).unwrap();
+        let snapshot = read_snapshot(&path).unwrap();
+        assert_eq!(snapshot.shell, None);
+        assert_eq!(snapshot.outputs.len(), 1);
+        assert_eq!(snapshot.outputs[0].shell, "true");
+    }
+}
@@ -0,0 +1,240 @@
+use crate::diff::{changed_ranges, Hunk, NO_NEWLINE};
+use std::io::IsTerminal;
+use std::ops::Range;
+
+/// When replay output is colored.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Colored when stdout is a terminal and `NO_COLOR` isn't set.
+    #[default]
+    Auto,
+    /// Always colored.
+    Always,
+    /// Never colored.
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output is colored.
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::io::stdout().is_terminal()
+                    && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Removed,
+    Added,
+    /// Changed words within a removed line.
+    RemovedWord,
+    /// Changed words within an added line.
+    AddedWord,
+    Header,
+    Marker,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Removed => "31",
+            Style::Added => "32",
+            Style::RemovedWord => "7;31",
+            Style::AddedWord => "7;32",
+            Style::Header => "36",
+            Style::Marker => "2",
+        }
+    }
+}
+
+/// Renders diffs, with ANSI colors when enabled.
+#[derive(Debug, Clone, Copy)]
+pub struct Painter {
+    color: bool,
+}
+
+impl Painter {
+    pub fn new(color: ColorChoice) -> Self {
+        Self {
+            color: color.enabled(),
+        }
+    }
+
+    fn paint(&self, text: &str, style: Style) -> String {
+        match self.color && !text.is_empty() {
+            true => format!("\x1b[{}m{}\x1b[0m", style.code(), text),
+            false => text.to_owned(),
+        }
+    }
+
+    /// The `--- expected` and `+++ actual` lines introducing a diff.
+    pub fn file_headers(&self) -> [String; 2] {
+        [
+            self.paint("--- expected", Style::Removed),
+            self.paint("+++ actual", Style::Added),
+        ]
+    }
+
+    /// The expected and actual value of a single line difference, with the changed words
+    /// highlighted.
+    pub fn single(&self, expected: &str, actual: &str) -> (String, String) {
+        self.pair("", expected, actual)
+    }
+
+    /// Lines of a hunk. A block of removed lines directly followed by added lines is paired up
+    /// line by line, highlighting the words that changed.
+    pub fn hunk(&self, hunk: &Hunk) -> Vec<String> {
+        let mut rendered = vec![self.paint(&hunk.header(), Style::Header)];
+        let lines = &hunk.lines;
+        let mut i = 0;
+        while i < lines.len() {
+            let (sign, text) = lines[i].split_at(1);
+            if sign != "-" {
+                rendered.push(match sign {
+                    "+" => self.changed(sign, true, text, &[], false),
+                    _ => format!("{}{}", sign, visible(text, usize::MAX, false)),
+                });
+                i += 1;
+                continue;
+            }
+
+            let signed = |from: usize, sign: char| {
+                from + lines[from..]
+                    .iter()
+                    .take_while(|line| line.starts_with(sign))
+                    .count()
+            };
+            let removed_end = signed(i, '-');
+            let added_end = signed(removed_end, '+');
+            let (removed, added) = (&lines[i..removed_end], &lines[removed_end..added_end]);
+            let mut rendered_added = Vec::with_capacity(added.len());
+            for (k, line) in removed.iter().enumerate() {
+                match added.get(k) {
+                    Some(other) => {
+                        let (line, other) = self.pair("-", &line[1..], &other[1..]);
+                        rendered.push(line);
+                        rendered_added.push(other);
+                    }
+                    None => rendered.push(self.changed("-", false, &line[1..], &[], false)),
+                }
+            }
+            for line in added.iter().skip(removed.len()) {
+                rendered_added.push(self.changed("+", true, &line[1..], &[], false));
+            }
+            rendered.extend(rendered_added);
+            i = added_end;
+        }
+        rendered
+    }
+
+    /// Renders the two versions of a changed line, `sign` being `-` for hunk lines or empty.
+    fn pair(&self, sign: &str, expected: &str, actual: &str) -> (String, String) {
+        let (removed, added) = changed_ranges(expected, actual);
+        let strip = |text: &str| text.split_whitespace().collect::<String>();
+        // Whitespace-only changes would be invisible without markers.
+        let whitespace_only = strip(expected) == strip(actual);
+        let added_sign = if sign.is_empty() { "" } else { "+" };
+        (
+            self.changed(sign, false, expected, &removed, whitespace_only),
+            self.changed(added_sign, true, actual, &added, whitespace_only),
+        )
+    }
+
+    /// Renders a removed or `added` line after its `sign`, emphasizing the `changed` byte ranges.
+    /// Trailing whitespace, and with `all_whitespace` any whitespace, is made visible.
+    fn changed(
+        &self,
+        sign: &str,
+        added: bool,
+        text: &str,
+        changed: &[Range<usize>],
+        all_whitespace: bool,
+    ) -> String {
+        let (style, word) = match added {
+            true => (Style::Added, Style::AddedWord),
+            false => (Style::Removed, Style::RemovedWord),
+        };
+        if text == NO_NEWLINE {
+            return format!(
+                "{}{}",
+                self.paint(sign, style),
+                self.paint(text, Style::Marker)
+            );
+        }
+
+        let trailing = text.trim_end().len();
+        let segment = |range: Range<usize>, style: Style| {
+            let from = trailing.saturating_sub(range.start);
+            self.paint(&visible(&text[range], from, all_whitespace), style)
+        };
+        let mut rendered = self.paint(sign, style);
+        let mut position = 0;
+        for range in changed.iter().chain([&(text.len()..text.len())]) {
+            rendered += &segment(position..range.start, style);
+            rendered += &segment(range.clone(), word);
+            position = range.end;
+        }
+        rendered
+    }
+}
+
+/// `text` with `\r` shown as `␍`, and spaces as `·` and tabs as `→` from byte `from` on (or
+/// everywhere with `all`).
+fn visible(text: &str, from: usize, all: bool) -> String {
+    text.char_indices()
+        .map(|(i, c)| match c {
+            '\r' => '␍',
+            ' ' if all || i >= from => '·',
+            '\t' if all || i >= from => '→',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::unified_diff;
+
+    fn painter(color: bool) -> Painter {
+        Painter { color }
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn test_plain_hunk() {
+        let hunks = unified_diff(&lines("a\nkeep\nb"), &lines("a\nkeep \nb\nc"), 1);
+        assert_eq!(
+            painter(false).hunk(&hunks[0]),
+            ["@@ -1,3 +1,4 @@", " a", "-keep", "+keep·", " b", "+c"]
+        );
+    }
+
+    #[test]
+    fn test_colored_pair() {
+        let (expected, actual) = painter(true).single("size 10", "size 12");
+        assert_eq!(expected, "\x1b[31msize \x1b[0m\x1b[7;31m10\x1b[0m");
+        assert_eq!(actual, "\x1b[32msize \x1b[0m\x1b[7;32m12\x1b[0m");
+    }
+
+    #[test]
+    fn test_whitespace_markers() {
+        let (expected, actual) = painter(false).single("a\tb", "a  b\r");
+        assert_eq!(expected, "a→b");
+        assert_eq!(actual, "a··b␍");
+        let (expected, actual) = painter(false).single("one two", "one three");
+        assert_eq!(
+            (expected.as_str(), actual.as_str()),
+            ("one two", "one three")
+        );
+    }
+}
@@ -0,0 +1,734 @@
+use crate::check::{ExitCheck, OutputCheck};
+use crate::config::{BuiltinRule, NormalizeConfig, NormalizeRule};
+use crate::normalize::Normalizer;
+use crate::test_toml::parse_test_toml;
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Annotation comment naming the next command.
+const NAME_ANNOTATION: &str = "@name:";
+/// Annotation comment adding tags to the next command.
+const TAGS_ANNOTATION: &str = "@tags:";
+/// Annotation comment setting the exit code the next command is expected to record.
+const EXPECT_EXIT_ANNOTATION: &str = "@expect-exit:";
+/// Annotation comment marking the next command as serial.
+const SERIAL_ANNOTATION: &str = "@serial";
+/// Annotation comment setting the timeout in seconds of the next command.
+const TIMEOUT_ANNOTATION: &str = "@timeout:";
+/// Annotation comment setting an environment variable of the next command.
+const ENV_ANNOTATION: &str = "@env:";
+/// Annotation comment setting the directory of the next command.
+const CD_ANNOTATION: &str = "@cd:";
+/// Annotation comment adding a line to the stdin of the next command.
+const STDIN_ANNOTATION: &str = "@stdin:";
+/// Annotation comment feeding a file to the stdin of the next command.
+const STDIN_FILE_ANNOTATION: &str = "@stdin-file:";
+/// Annotation comment adding a normalization rule to the next command.
+const NORMALIZE_ANNOTATION: &str = "@normalize:";
+/// Separates the pattern and replacement of a custom normalization rule.
+const NORMALIZE_SEPARATOR: &str = " => ";
+/// Annotation comment setting how the next command's stdout is compared.
+const STDOUT_ANNOTATION: &str = "@stdout:";
+/// Annotation comment setting how the next command's stderr is compared.
+const STDERR_ANNOTATION: &str = "@stderr:";
+/// Annotation comment setting how the next command's exit code is compared.
+const EXIT_CODE_ANNOTATION: &str = "@exit-code:";
+/// Annotation comment inserting the tests of another test file.
+const INCLUDE_ANNOTATION: &str = "@include";
+/// Every annotation, a comment starting with `@` that isn't one of these is an error.
+const ANNOTATIONS: &[&str] = &[
+    NAME_ANNOTATION,
+    TAGS_ANNOTATION,
+    EXPECT_EXIT_ANNOTATION,
+    SERIAL_ANNOTATION,
+    TIMEOUT_ANNOTATION,
+    ENV_ANNOTATION,
+    CD_ANNOTATION,
+    STDIN_ANNOTATION,
+    STDIN_FILE_ANNOTATION,
+    NORMALIZE_ANNOTATION,
+    STDOUT_ANNOTATION,
+    STDERR_ANNOTATION,
+    EXIT_CODE_ANNOTATION,
+    INCLUDE_ANNOTATION,
+];
+/// Opens and closes a multi-line command block.
+const FENCE: &str = "```";
+/// Heredoc operator at the start of the text, capturing the `-` of `<<-` and the delimiter, see
+/// [`heredoc_delimiters`].
+static HEREDOC: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^<<(-?)[ \t]*['"]?([A-Za-z_][A-Za-z0-9_]*)['"]?"#).unwrap());
+
+/// A single command from the test file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TestCase {
+    pub shell: String,
+    /// Unique name used in reports instead of the command.
+    pub name: Option<String>,
+    /// Tags for selecting the test.
+    pub tags: Vec<String>,
+    /// Exit code the command has to exit with when recorded.
+    pub expect_exit: Option<i32>,
+    /// Whether the command must not run concurrently with any other command.
+    pub serial: bool,
+    /// Timeout overriding the configured default.
+    pub timeout: Option<Duration>,
+    /// Environment variables overriding the configured ones.
+    pub env: Vec<(String, String)>,
+    /// Directory relative to the configured working directory.
+    pub cd: Option<PathBuf>,
+    /// Input fed to the command, `/dev/null` when unset.
+    pub stdin: Option<Vec<u8>>,
+    /// Normalization rules applied after the configured ones.
+    pub normalize: NormalizeConfig,
+    /// How stdout is compared on replay.
+    pub stdout: OutputCheck,
+    /// How stderr is compared on replay.
+    pub stderr: OutputCheck,
+    /// How the exit code is compared on replay.
+    pub exit_code: ExitCheck,
+}
+
+impl TestCase {
+    /// How the test is referred to in output, its name or else its command.
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.shell)
+    }
+}
+
+/// Loads the commands from the test file, skipping blank lines and `//` comments. Commands can
+/// span several lines, see [`read_command`]. Annotation comments apply to the command following
+/// them:
+/// - `// @name: <name>`: Names the test, names have to be unique.
+/// - `// @tags: <tag>, <tag>`: Adds tags to the test, can be repeated.
+/// - `// @expect-exit: <code>`: Fails the record if the command exits with another code.
+/// - `// @serial`: The command is serial.
+/// - `// @timeout: <seconds>`: Overrides the default timeout.
+/// - `// @env: <NAME>=<value>`: Sets an environment variable, can be repeated.
+/// - `// @cd: <dir>`: Runs the command from `dir`, relative to the configured working directory.
+/// - `// @stdin: <line>`: Adds a line to the command's stdin, can be repeated.
+/// - `// @stdin-file: <path>`: Feeds a file, relative to the test file, to the command's stdin.
+/// - `// @normalize: <builtin>` or `// @normalize: <regex> => <replacement>`: Adds a rule.
+/// - `// @stdout: <mode>` and `// @stderr: <mode>`: Sets how the stream is compared, see
+///   [`OutputCheck::parse`].
+/// - `// @exit-code: <mode>`: Sets how the exit code is compared, `exact` or `ignore`.
+///
+/// `// @include <path>` inserts the tests of another test file, relative to this one, in place.
+/// Including a file that is already being loaded is an error.
+///
+/// Any other comment starting with `@`, and annotations that no command follows, are errors.
+///
+/// A test file with the `.toml` extension is read as structured test definitions instead, see
+/// [`parse_test_toml`].
+pub fn load_test_cases(test_path: &Path) -> Result<Vec<TestCase>> {
+    let tests = load_file(test_path, &mut Vec::new())?;
+
+    if tests.is_empty() {
+        anyhow::bail!("Test file is empty or contains only comments.");
+    }
+    // Each file checks the names it sees, tests of sibling includes can still collide.
+    let mut names = HashSet::new();
+    if let Some(name) = tests
+        .iter()
+        .filter_map(|test| test.name.as_deref())
+        .find(|name| !names.insert(*name))
+    {
+        anyhow::bail!("Duplicate test name `{}`", name);
+    }
+
+    Ok(tests)
+}
+
+/// Loads a test file and, recursively, the files it includes. `stack` holds the canonical paths
+/// of the files currently being loaded to detect include cycles.
+fn load_file(test_path: &Path, stack: &mut Vec<PathBuf>) -> Result<Vec<TestCase>> {
+    let canonical = test_path
+        .canonicalize()
+        .with_context(|| format!("Test file not found at: {}", test_path.display()))?;
+    if let Some(start) = stack.iter().position(|path| *path == canonical) {
+        let cycle: Vec<String> = stack[start..]
+            .iter()
+            .chain([&canonical])
+            .map(|path| path.display().to_string())
+            .collect();
+        anyhow::bail!("Include cycle: {}", cycle.join(" -> "));
+    }
+
+    let content = std::fs::read_to_string(test_path)?;
+    let base_dir = test_path.parent().unwrap_or(Path::new("."));
+    stack.push(canonical);
+    let mut include = |path: &Path| load_file(path, stack);
+    let tests = match test_path.extension().is_some_and(|ext| ext == "toml") {
+        true => parse_test_toml(&content, base_dir, &mut include),
+        false => parse_test_cases(&content, base_dir, &mut include),
+    }
+    .with_context(|| format!("Invalid test file {}", test_path.display()))?;
+    stack.pop();
+
+    Ok(tests)
+}
+
+/// Parses the test file content, reading `@stdin-file` paths relative to `base_dir` and the tests
+/// of `@include` paths (also relative to `base_dir`) with `include`.
+fn parse_test_cases(
+    content: &str,
+    base_dir: &Path,
+    include: &mut dyn FnMut(&Path) -> Result<Vec<TestCase>>,
+) -> Result<Vec<TestCase>> {
+    let mut tests: Vec<TestCase> = Vec::new();
+    // Annotations for the next command.
+    let mut pending = TestCase::default();
+    // Line of the last annotation, reported when no command follows it.
+    let mut annotation_line = 0;
+    let mut stdin_file = false;
+
+    let mut lines = content.lines().enumerate();
+    while let Some((number, line)) = lines.next() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix("//") {
+            let comment = comment.trim();
+            if comment.starts_with('@') {
+                annotation_line = number + 1;
+            }
+            if let Some(value) = comment.strip_prefix(NAME_ANNOTATION) {
+                let value = value.trim();
+                if value.is_empty() {
+                    anyhow::bail!("Line {}: missing name", number + 1);
+                }
+                if tests.iter().any(|test| test.name.as_deref() == Some(value)) {
+                    anyhow::bail!("Line {}: duplicate test name `{}`", number + 1, value);
+                }
+                pending.name = Some(value.to_owned());
+            } else if let Some(value) = comment.strip_prefix(TAGS_ANNOTATION) {
+                pending.tags.extend(
+                    value
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|tag| !tag.is_empty())
+                        .map(str::to_owned),
+                );
+            } else if let Some(value) = comment.strip_prefix(EXPECT_EXIT_ANNOTATION) {
+                let code = value.trim().parse().with_context(|| {
+                    format!(
+                        "Line {}: `{}` is not an exit code",
+                        number + 1,
+                        value.trim()
+                    )
+                })?;
+                pending.expect_exit = Some(code);
+            } else if comment == SERIAL_ANNOTATION {
+                pending.serial = true;
+            } else if let Some(value) = comment.strip_prefix(TIMEOUT_ANNOTATION) {
+                pending.timeout = Some(
+                    parse_timeout(value.trim())
+                        .with_context(|| format!("Line {}: invalid timeout", number + 1))?,
+                );
+            } else if let Some(value) = comment.strip_prefix(ENV_ANNOTATION) {
+                pending.env.push(
+                    parse_env(value.trim())
+                        .with_context(|| format!("Line {}: invalid env", number + 1))?,
+                );
+            } else if let Some(value) = comment.strip_prefix(CD_ANNOTATION) {
+                let dir = value.trim();
+                if dir.is_empty() {
+                    anyhow::bail!("Line {}: missing directory", number + 1);
+                }
+                pending.cd = Some(PathBuf::from(dir));
+            } else if let Some(value) = comment.strip_prefix(STDIN_ANNOTATION) {
+                if stdin_file {
+                    anyhow::bail!("Line {}: stdin is already read from a file", number + 1);
+                }
+                let input = pending.stdin.get_or_insert_with(Vec::new);
+                input.extend_from_slice(value.strip_prefix(' ').unwrap_or(value).as_bytes());
+                input.push(b'\n');
+            } else if let Some(value) = comment.strip_prefix(STDIN_FILE_ANNOTATION) {
+                if pending.stdin.is_some() {
+                    anyhow::bail!("Line {}: stdin is already set", number + 1);
+                }
+                let path = base_dir.join(value.trim());
+                pending.stdin = Some(std::fs::read(&path).with_context(|| {
+                    format!("Line {}: cannot read {}", number + 1, path.display())
+                })?);
+                stdin_file = true;
+            } else if let Some(value) = comment.strip_prefix(NORMALIZE_ANNOTATION) {
+                parse_normalize(value.trim(), &mut pending.normalize)
+                    .with_context(|| format!("Line {}: invalid normalization rule", number + 1))?;
+            } else if let Some(value) = comment.strip_prefix(STDOUT_ANNOTATION) {
+                pending.stdout = OutputCheck::parse(value.trim())
+                    .with_context(|| format!("Line {}: invalid stdout mode", number + 1))?;
+            } else if let Some(value) = comment.strip_prefix(STDERR_ANNOTATION) {
+                pending.stderr = OutputCheck::parse(value.trim())
+                    .with_context(|| format!("Line {}: invalid stderr mode", number + 1))?;
+            } else if let Some(value) = comment.strip_prefix(EXIT_CODE_ANNOTATION) {
+                pending.exit_code = ExitCheck::parse(value.trim())
+                    .with_context(|| format!("Line {}: invalid exit code mode", number + 1))?;
+            } else if let Some(value) = comment
+                .strip_prefix(INCLUDE_ANNOTATION)
+                .filter(|value| value.is_empty() || value.starts_with(char::is_whitespace))
+            {
+                if pending != TestCase::default() {
+                    anyhow::bail!("Line {}: annotations can't apply to an include", number + 1);
+                }
+                if value.trim().is_empty() {
+                    anyhow::bail!("Line {}: missing include path", number + 1);
+                }
+                let path = base_dir.join(value.trim());
+                tests.extend(include(&path).with_context(|| format!("Line {}", number + 1))?);
+            } else if comment.starts_with('@') {
+                let name = comment
+                    .split(|c: char| c == ':' || c.is_whitespace())
+                    .next()
+                    .unwrap_or(comment);
+                match ANNOTATIONS
+                    .iter()
+                    .any(|known| known.trim_end_matches(':') == name)
+                {
+                    true => anyhow::bail!("Line {}: invalid `{}` annotation", number + 1, name),
+                    false => anyhow::bail!("Line {}: unknown annotation `{}`", number + 1, name),
+                }
+            }
+            continue;
+        }
+        tests.push(TestCase {
+            shell: read_command(number, trimmed, &mut lines)?,
+            ..std::mem::take(&mut pending)
+        });
+        stdin_file = false;
+    }
+
+    if pending != TestCase::default() {
+        anyhow::bail!(
+            "Line {}: annotations aren't followed by a command",
+            annotation_line
+        );
+    }
+    Ok(tests)
+}
+
+/// Reads the command starting at `first`, taking any further lines it spans from `lines`:
+/// - A ```` ``` ```` fence starts a block up to the closing fence, its exact text is the command.
+/// - A line ending in `\` continues on the next line.
+/// - A heredoc (`<<EOF`, `<<-EOF`) continues up to its delimiter line.
+fn read_command<'a>(
+    number: usize,
+    first: &str,
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> Result<String> {
+    if first.starts_with(FENCE) {
+        let mut block = Vec::new();
+        for (_, line) in lines.by_ref() {
+            if line.trim() == FENCE {
+                if block.iter().all(|line: &&str| line.trim().is_empty()) {
+                    anyhow::bail!("Line {}: empty block", number + 1);
+                }
+                return Ok(block.join("\n"));
+            }
+            block.push(line);
+        }
+        anyhow::bail!("Line {}: unterminated {} block", number + 1, FENCE);
+    }
+
+    let mut command = first.to_owned();
+    while command.ends_with('\\') {
+        let Some((_, line)) = lines.next() else {
+            break;
+        };
+        command.push('\n');
+        command.push_str(line);
+    }
+
+    for (strip_tabs, delimiter) in heredoc_delimiters(&command) {
+        loop {
+            let Some((_, line)) = lines.next() else {
+                anyhow::bail!(
+                    "Line {}: missing heredoc delimiter `{}`",
+                    number + 1,
+                    delimiter
+                );
+            };
+            command.push('\n');
+            command.push_str(line);
+            let end = match strip_tabs {
+                true => line.trim_start_matches('\t'),
+                false => line,
+            };
+            if end == delimiter {
+                break;
+            }
+        }
+    }
+    Ok(command)
+}
+
+/// Heredocs opened by `command`, as whether `<<-` strips leading tabs and the delimiter. Only
+/// operators outside quotes and arithmetic (`$((a << b))`, `((a << b))`) count, and here-strings
+/// (`<<<`) are not heredocs.
+fn heredoc_delimiters(command: &str) -> Vec<(bool, String)> {
+    let bytes = command.as_bytes();
+    let mut delimiters = Vec::new();
+    let mut quote = None;
+    // Parentheses opened inside each enclosing arithmetic expression.
+    let mut arithmetic: Vec<usize> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match (quote, bytes[i]) {
+            (Some(b'\''), b'\'') | (Some(b'"'), b'"') => quote = None,
+            (Some(b'"'), b'\\') | (None, b'\\') => i += 1,
+            (Some(_), _) => {}
+            (None, b'\'' | b'"') => quote = Some(bytes[i]),
+            (None, b'(') if bytes.get(i + 1) == Some(&b'(') => {
+                arithmetic.push(0);
+                i += 1;
+            }
+            (None, b'(') => {
+                if let Some(open) = arithmetic.last_mut() {
+                    *open += 1;
+                }
+            }
+            (None, b')') => match arithmetic.last_mut() {
+                Some(0) if bytes.get(i + 1) == Some(&b')') => {
+                    arithmetic.pop();
+                    i += 1;
+                }
+                Some(open) => *open = open.saturating_sub(1),
+                None => {}
+            },
+            (None, b'<') if arithmetic.is_empty() && (i == 0 || bytes[i - 1] != b'<') => {
+                if let Some(captures) = HEREDOC.captures(&command[i..]) {
+                    delimiters.push((!captures[1].is_empty(), captures[2].to_owned()));
+                    i += captures[0].len();
+                    continue;
+                }
+            }
+            (None, _) => {}
+        }
+        i += 1;
+    }
+    delimiters
+}
+
+/// Parses a positive number of seconds, e.g. `30` or `0.5`.
+fn parse_timeout(seconds: &str) -> Result<Duration> {
+    let seconds: f64 = seconds
+        .parse()
+        .with_context(|| format!("`{}` is not a number of seconds", seconds))?;
+    timeout_from_secs(seconds)
+}
+
+/// Parses a `NAME=value` assignment.
+fn parse_env(assignment: &str) -> Result<(String, String)> {
+    match assignment.split_once('=') {
+        Some((name, value)) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+            Ok((name.to_owned(), value.to_owned()))
+        }
+        _ => anyhow::bail!("`{}` is not a NAME=value assignment", assignment),
+    }
+}
+
+/// Parses a built-in rule name or a `<regex> => <replacement>` rule into `normalize`.
+fn parse_normalize(rule: &str, normalize: &mut NormalizeConfig) -> Result<()> {
+    match rule.split_once(NORMALIZE_SEPARATOR) {
+        Some((pattern, replacement)) => {
+            let rule = NormalizeRule {
+                pattern: pattern.trim().to_owned(),
+                replacement: replacement.trim().to_owned(),
+            };
+            // Reports an invalid pattern at its line rather than when the test runs.
+            Normalizer::new(&NormalizeConfig {
+                builtin: Vec::new(),
+                rule: vec![rule.clone()],
+            })?;
+            normalize.rule.push(rule);
+        }
+        None => match BuiltinRule::from_name(rule) {
+            Some(builtin) => normalize.builtin.push(builtin),
+            None => anyhow::bail!("`{}` is not a built-in rule", rule),
+        },
+    }
+    Ok(())
+}
+
+/// Converts a timeout in seconds, which must be positive, to a `Duration`.
+pub fn timeout_from_secs(seconds: f64) -> Result<Duration> {
+    // Rejects negative, NaN, and values too large for a `Duration` rather than panicking.
+    Duration::try_from_secs_f64(seconds)
+        .ok()
+        .filter(|timeout| !timeout.is_zero())
+        .context("timeout must be a positive number of seconds")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str, base_dir: &Path) -> Result<Vec<TestCase>> {
+        parse_test_cases(content, base_dir, &mut |path| {
+            anyhow::bail!("unexpected include of {}", path.display())
+        })
+    }
+
+    #[test]
+    fn test_parse_test_cases() {
+        let content = "// comment\necho a\n\n// @name: clean\n// @tags: fs, slow\n// @tags: ci\n\
+                       // @expect-exit: 0\n// @serial\n// @timeout: 1.5\n// @env: TZ=UTC\n\
+                       // @env: EMPTY=\n// @cd: out/..\nrm -rf out\necho b\n";
+        let tests = parse(content, Path::new(".")).unwrap();
+        assert_eq!(
+            tests,
+            vec![
+                TestCase {
+                    shell: "echo a".to_owned(),
+                    name: None,
+                    tags: Vec::new(),
+                    expect_exit: None,
+                    serial: false,
+                    timeout: None,
+                    env: Vec::new(),
+                    cd: None,
+                    stdin: None,
+                    ..Default::default()
+                },
+                TestCase {
+                    shell: "rm -rf out".to_owned(),
+                    name: Some("clean".to_owned()),
+                    tags: vec!["fs".to_owned(), "slow".to_owned(), "ci".to_owned()],
+                    expect_exit: Some(0),
+                    serial: true,
+                    timeout: Some(Duration::from_millis(1500)),
+                    env: vec![
+                        ("TZ".to_owned(), "UTC".to_owned()),
+                        ("EMPTY".to_owned(), String::new()),
+                    ],
+                    cd: Some(PathBuf::from("out/..")),
+                    stdin: None,
+                    ..Default::default()
+                },
+                TestCase {
+                    shell: "echo b".to_owned(),
+                    name: None,
+                    tags: Vec::new(),
+                    expect_exit: None,
+                    serial: false,
+                    timeout: None,
+                    env: Vec::new(),
+                    cd: None,
+                    stdin: None,
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_timeout() {
+        let error = parse("// @timeout: soon\necho a\n", Path::new(".")).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: invalid timeout");
+        assert!(parse("// @timeout: 0\necho a\n", Path::new(".")).is_err());
+        let error = parse("// @timeout: 1e20\necho a\n", Path::new(".")).unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "Line 1: invalid timeout: timeout must be a positive number of seconds"
+        );
+        assert!(timeout_from_secs(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_invalid_env() {
+        let error = parse("echo a\n// @env: TZ\necho b\n", Path::new(".")).unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: invalid env");
+        assert!(parse("// @env: =UTC\necho a\n", Path::new(".")).is_err());
+        let error = parse("// @cd:\necho a\n", Path::new(".")).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: missing directory");
+    }
+
+    #[test]
+    fn test_parse_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("input.txt"), "from file\n").unwrap();
+        let content = "// @stdin: first\n// @stdin:  indented\ncat\n\
+                       // @stdin-file: input.txt\ncat\ncat\n";
+        let tests = parse(content, dir.path()).unwrap();
+        assert_eq!(tests[0].stdin.as_deref(), Some(&b"first\n indented\n"[..]));
+        assert_eq!(tests[1].stdin.as_deref(), Some(&b"from file\n"[..]));
+        assert_eq!(tests[2].stdin, None);
+
+        let error =
+            parse("// @stdin: a\n// @stdin-file: input.txt\ncat\n", dir.path()).unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: stdin is already set");
+        let error = parse("// @stdin-file: missing.txt\ncat\n", dir.path()).unwrap_err();
+        assert!(error.to_string().starts_with("Line 1: cannot read"));
+    }
+
+    #[test]
+    fn test_invalid_annotations() {
+        let dir = Path::new(".");
+        let error = parse("// @name: a\necho\n// @name: a\necho\n", dir).unwrap_err();
+        assert_eq!(error.to_string(), "Line 3: duplicate test name `a`");
+        let error = parse("// @name:\necho\n", dir).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: missing name");
+        let error = parse("// @expect-exit: one\necho\n", dir).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: `one` is not an exit code");
+
+        let error = parse("// @timout: 5\necho\n", dir).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: unknown annotation `@timout`");
+        let error = parse("echo\n// @includes other.list\n", dir).unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: unknown annotation `@includes`");
+        let error = parse("// @timeout 5\necho\n", dir).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: invalid `@timeout` annotation");
+        let error = parse("// @serial please\necho\n", dir).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: invalid `@serial` annotation");
+        let error = parse("echo\n// @include\n", dir).unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: missing include path");
+        // Plain comments can still mention annotations.
+        assert!(parse("// runs @serial below\necho\n", dir).is_ok());
+
+        let error = parse("echo\n// @serial\n// @timeout: 5\n// done\n", dir).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Line 3: annotations aren't followed by a command"
+        );
+    }
+
+    #[test]
+    fn test_parse_normalize() {
+        let content = "// @normalize: uuid\n// @normalize: took \\d+ms => took <N>ms\necho a\n";
+        let tests = parse(content, Path::new(".")).unwrap();
+        assert_eq!(
+            tests[0].normalize,
+            NormalizeConfig {
+                builtin: vec![BuiltinRule::Uuid],
+                rule: vec![NormalizeRule {
+                    pattern: r"took \d+ms".to_owned(),
+                    replacement: "took <N>ms".to_owned(),
+                }],
+            }
+        );
+
+        let error = parse("// @normalize: dates\necho a\n", Path::new(".")).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: invalid normalization rule");
+        assert_eq!(
+            error.root_cause().to_string(),
+            "`dates` is not a built-in rule"
+        );
+        assert!(parse("// @normalize: ( => x\necho a\n", Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn test_parse_comparison_modes() {
+        let content =
+            "// @stdout: contains ready\n// @stderr: ignore\n// @exit-code: ignore\nserve\n";
+        let tests = parse(content, Path::new(".")).unwrap();
+        assert_eq!(tests[0].stdout, OutputCheck::Contains("ready".to_owned()));
+        assert_eq!(tests[0].stderr, OutputCheck::Ignore);
+        assert_eq!(tests[0].exit_code, ExitCheck::Ignore);
+
+        let error = parse("// @stdout: sorted\necho\n", Path::new(".")).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: invalid stdout mode");
+    }
+
+    #[test]
+    fn test_label() {
+        let tests = parse("// @name: greet\necho hi\necho bye\n", Path::new(".")).unwrap();
+        assert_eq!(tests[0].label(), "greet");
+        assert_eq!(tests[1].label(), "echo bye");
+    }
+
+    #[test]
+    fn test_multi_line_commands() {
+        let content = "echo one \\\n  two\n\
+                       ```sh\nfor i in 1 2; do\n    echo \"$i  \"\ndone\n```\n\
+                       cat <<-'EOF' | sort\n\tb\n\ta\n\tEOF\n\
+                       cat <<<here\n";
+        let tests = parse(content, Path::new(".")).unwrap();
+        let commands: Vec<_> = tests.iter().map(|test| test.shell.as_str()).collect();
+        assert_eq!(
+            commands,
+            [
+                "echo one \\\n  two",
+                "for i in 1 2; do\n    echo \"$i  \"\ndone",
+                "cat <<-'EOF' | sort\n\tb\n\ta\n\tEOF",
+                "cat <<<here",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_blocks() {
+        let dir = Path::new(".");
+        let error = parse("echo a\n```\necho b\n", dir).unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: unterminated ``` block");
+        let error = parse("```\n\n```\n", dir).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: empty block");
+        let error = parse("cat <<END\nbody\n", dir).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: missing heredoc delimiter `END`");
+    }
+
+    #[test]
+    fn test_heredoc_delimiters() {
+        assert_eq!(
+            heredoc_delimiters("cat <<EOF && cat <<-'END' <<< word"),
+            [(false, "EOF".to_owned()), (true, "END".to_owned())]
+        );
+        // Shifts in arithmetic and quoted text aren't heredocs.
+        for command in [
+            "echo $((a<<b))",
+            "echo $(( (1 << 2) + (3<<4) ))",
+            "(( x = 1 << 2 ))",
+            "echo '<<EOF'",
+            "echo \"a <<EOF b\"",
+            "echo \"it's\" \\<<EOF",
+        ] {
+            assert!(heredoc_delimiters(command).is_empty(), "{}", command);
+        }
+        assert_eq!(
+            heredoc_delimiters("echo $((1<<2)) \"'\" | cat <<EOF"),
+            [(false, "EOF".to_owned())]
+        );
+
+        let tests = parse("echo $((1<<2))\necho '<<EOF'\necho done\n", Path::new(".")).unwrap();
+        assert_eq!(tests.len(), 3);
+    }
+
+    #[test]
+    fn test_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, content: &str| std::fs::write(dir.path().join(name), content);
+        write("main.list", "echo a\n// @include sub/common.list\necho d\n").unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        write("sub/common.list", "echo b\n// @include more.toml\n").unwrap();
+        write("sub/more.toml", "[[test]]\ncommand = \"echo c\"\n").unwrap();
+        let tests = load_test_cases(&dir.path().join("main.list")).unwrap();
+        let commands: Vec<_> = tests.iter().map(|test| test.shell.as_str()).collect();
+        assert_eq!(commands, ["echo a", "echo b", "echo c", "echo d"]);
+
+        write("sub/more.toml", "include = [\"../main.list\"]\n").unwrap();
+        let error = load_test_cases(&dir.path().join("main.list")).unwrap_err();
+        assert!(error
+            .root_cause()
+            .to_string()
+            .starts_with("Include cycle: "));
+
+        write("main.list", "// @name: a\n// @include sub/common.list\n").unwrap();
+        let error = load_test_cases(&dir.path().join("main.list")).unwrap_err();
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Line 2: annotations can't apply to an include"
+        );
+
+        write("sub/common.list", "// @name: a\necho b\n").unwrap();
+        write("sub/more.list", "// @name: a\necho c\n").unwrap();
+        write(
+            "main.list",
+            "// @include sub/common.list\n// @include sub/more.list\n",
+        )
+        .unwrap();
+        let error = load_test_cases(&dir.path().join("main.list")).unwrap_err();
+        assert_eq!(error.to_string(), "Duplicate test name `a`");
+    }
+}
@@ -0,0 +1,249 @@
+use crate::check::{ExitCheck, OutputCheck};
+use crate::config::NormalizeConfig;
+use crate::normalize::Normalizer;
+use crate::test_list::{timeout_from_secs, TestCase};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Structured test file, a `[[test]]` table per command.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestFile {
+    /// Test files whose tests come before this file's, relative to this file.
+    #[serde(default)]
+    include: Vec<PathBuf>,
+    #[serde(default, rename = "test")]
+    tests: Vec<TestDefinition>,
+}
+
+/// A single `[[test]]` table, the structured counterpart of a command and its annotations.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestDefinition {
+    command: String,
+    name: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    expect_exit: Option<i32>,
+    #[serde(default)]
+    serial: bool,
+    timeout: Option<f64>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    workdir: Option<PathBuf>,
+    stdin: Option<String>,
+    stdin_file: Option<PathBuf>,
+    #[serde(default)]
+    normalize: NormalizeConfig,
+    #[serde(default)]
+    stdout: OutputCheck,
+    #[serde(default)]
+    stderr: OutputCheck,
+    #[serde(default)]
+    exit_code: ExitCheck,
+}
+
+/// Parses a `tests.toml` file into the same tests as the plain test file, reading `stdin_file`
+/// paths relative to `base_dir` and the tests of `include` paths (also relative to `base_dir`)
+/// with `include`.
+pub fn parse_test_toml(
+    content: &str,
+    base_dir: &Path,
+    include: &mut dyn FnMut(&Path) -> Result<Vec<TestCase>>,
+) -> Result<Vec<TestCase>> {
+    let file: TestFile = toml::from_str(content)?;
+
+    let mut tests: Vec<TestCase> = Vec::with_capacity(file.tests.len());
+    for path in &file.include {
+        tests.extend(
+            include(&base_dir.join(path)).with_context(|| format!("Include {}", path.display()))?,
+        );
+    }
+    for (number, definition) in file.tests.into_iter().enumerate() {
+        let label = match &definition.name {
+            Some(name) => format!("Test {} (`{}`)", number + 1, name),
+            None => format!("Test {}", number + 1),
+        };
+        let test = to_test_case(definition, &tests, base_dir).context(label)?;
+        tests.push(test);
+    }
+    Ok(tests)
+}
+
+/// Validates a definition against the tests before it and converts it.
+fn to_test_case(
+    definition: TestDefinition,
+    previous: &[TestCase],
+    base_dir: &Path,
+) -> Result<TestCase> {
+    if definition.command.trim().is_empty() {
+        anyhow::bail!("empty command");
+    }
+    if let Some(name) = &definition.name {
+        if name.trim().is_empty() {
+            anyhow::bail!("empty name");
+        }
+        if previous.iter().any(|test| test.name.as_ref() == Some(name)) {
+            anyhow::bail!("duplicate test name `{}`", name);
+        }
+    }
+    if let Some(name) = definition
+        .env
+        .keys()
+        .find(|name| name.is_empty() || name.contains(['=', '\0']))
+    {
+        anyhow::bail!("`{}` is not an environment variable name", name);
+    }
+
+    Normalizer::new(&definition.normalize)?;
+
+    let stdin = match (definition.stdin, definition.stdin_file) {
+        (Some(_), Some(_)) => anyhow::bail!("`stdin` and `stdin_file` can't both be set"),
+        (Some(stdin), None) => Some(stdin.into_bytes()),
+        (None, Some(path)) => {
+            let path = base_dir.join(path);
+            Some(std::fs::read(&path).with_context(|| format!("cannot read {}", path.display()))?)
+        }
+        (None, None) => None,
+    };
+
+    Ok(TestCase {
+        shell: definition.command,
+        name: definition.name,
+        tags: definition.tags,
+        expect_exit: definition.expect_exit,
+        serial: definition.serial,
+        timeout: definition
+            .timeout
+            .map(timeout_from_secs)
+            .transpose()
+            .context("invalid timeout")?,
+        env: definition.env.into_iter().collect(),
+        cd: definition.workdir,
+        stdin,
+        normalize: definition.normalize,
+        stdout: definition.stdout,
+        stderr: definition.stderr,
+        exit_code: definition.exit_code,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn parse(content: &str, base_dir: &Path) -> Result<Vec<TestCase>> {
+        parse_test_toml(content, base_dir, &mut |path| {
+            anyhow::bail!("unexpected include of {}", path.display())
+        })
+    }
+
+    #[test]
+    fn test_parse_test_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("input.txt"), "from file\n").unwrap();
+        let content = r#"
+[[test]]
+name = "greet"
+command = "echo hi"
+tags = ["fast"]
+
+[[test]]
+command = """
+sort
+"""
+stdin_file = "input.txt"
+env = { TZ = "UTC", LANG = "C" }
+workdir = "sub"
+timeout = 2.5
+expect_exit = 0
+serial = true
+"#;
+        let tests = parse(content, dir.path()).unwrap();
+        assert_eq!(
+            tests,
+            vec![
+                TestCase {
+                    shell: "echo hi".to_owned(),
+                    name: Some("greet".to_owned()),
+                    tags: vec!["fast".to_owned()],
+                    expect_exit: None,
+                    serial: false,
+                    timeout: None,
+                    env: Vec::new(),
+                    cd: None,
+                    stdin: None,
+                    ..Default::default()
+                },
+                TestCase {
+                    shell: "sort\n".to_owned(),
+                    name: None,
+                    tags: Vec::new(),
+                    expect_exit: Some(0),
+                    serial: true,
+                    timeout: Some(Duration::from_millis(2500)),
+                    env: vec![
+                        ("LANG".to_owned(), "C".to_owned()),
+                        ("TZ".to_owned(), "UTC".to_owned()),
+                    ],
+                    cd: Some(PathBuf::from("sub")),
+                    stdin: Some(b"from file\n".to_vec()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comparison_modes() {
+        let content = "[[test]]\ncommand = \"ls\"\nstdout = \"unordered\"\n\
+                       stderr = { regex = \"^warn\" }\nexit_code = \"ignore\"\n";
+        let tests = parse(content, Path::new(".")).unwrap();
+        assert_eq!(tests[0].stdout, OutputCheck::Unordered);
+        assert_eq!(
+            tests[0].stderr,
+            OutputCheck::Regex(crate::check::Pattern::new("^warn").unwrap())
+        );
+        assert_eq!(tests[0].exit_code, ExitCheck::Ignore);
+
+        let invalid = "[[test]]\ncommand = \"ls\"\nstdout = { regex = \"(\" }\n";
+        assert!(parse(invalid, Path::new(".")).is_err());
+        let empty = "[[test]]\ncommand = \"ls\"\nstdout = { contains = \"\" }\n";
+        let error = parse(empty, Path::new(".")).unwrap_err();
+        assert!(format!("{:#}", error).contains("`contains` needs a non-empty text"));
+    }
+
+    #[test]
+    fn test_invalid_test_toml() {
+        let dir = Path::new(".");
+        let duplicate = "[[test]]\nname = \"a\"\ncommand = \"true\"\n\
+                         [[test]]\nname = \"a\"\ncommand = \"true\"\n";
+        let error = parse(duplicate, dir).unwrap_err();
+        assert_eq!(error.to_string(), "Test 2 (`a`)");
+        assert_eq!(error.root_cause().to_string(), "duplicate test name `a`");
+
+        let error = parse(
+            "[[test]]\ncommand = \"cat\"\nstdin = \"\"\nstdin_file = \"x\"\n",
+            dir,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.root_cause().to_string(),
+            "`stdin` and `stdin_file` can't both be set"
+        );
+
+        assert!(parse("[[test]]\ncommand = \"true\"\ntimeout = -1\n", dir).is_err());
+        assert!(parse("[[test]]\ncommand = \"true\"\ncmd = \"x\"\n", dir).is_err());
+        assert!(parse("[[test]]\nname = \"no command\"\n", dir).is_err());
+        let error = parse(
+            "[[test]]\ncommand = \"true\"\nnormalize = { rule = [{ pattern = \"(\", \
+             replacement = \"\" }] }\n",
+            dir,
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", error).contains("invalid normalization pattern `(`"));
+    }
+}
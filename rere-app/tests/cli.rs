@@ -134,3 +134,893 @@ fn test_clean_all() {
     // Verify rere directory no longer exists
     assert!(!temp.path().join("rere").exists());
 }
+
+#[test]
+fn test_replay_corrupted_snapshot() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    fs::write(temp.path().join("rere/test.list"), "echo 'test'\n").unwrap();
+    rere_cmd().arg(&config_path).arg("record").output().unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(output.status.success());
+
+    // Same length edit, so only the checksum catches it.
+    let snapshot_path = temp.path().join("rere/snapshots/test.list.bi");
+    let snapshot = fs::read_to_string(&snapshot_path).unwrap();
+    assert!(snapshot.contains(":i bi.crc32 "));
+    fs::write(&snapshot_path, snapshot.replace("test\n\n", "tezt\n\n")).unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success());
+    assert!(stderr.contains("is corrupted"));
+    assert!(stderr.contains("Checksum mismatch"));
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_record_compressed() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config.replace("compress = false", "compress = true"),
+    )
+    .unwrap();
+    fs::write(
+        temp.path().join("rere/test.list"),
+        "yes log | head -n 5000\n",
+    )
+    .unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+
+    let snapshot = fs::read(temp.path().join("rere/snapshots/test.list.bi")).unwrap();
+    assert!(snapshot.windows(10).any(|w| w == b":z stdout "));
+    assert!(snapshot.len() < 10_000);
+
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_parallel_jobs() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    fs::write(
+        temp.path().join("rere/test.list"),
+        "sleep 0.3; echo first\necho second\n// @serial\necho third\necho fourth\n",
+    )
+    .unwrap();
+
+    let output = rere_cmd()
+        .arg(&config_path)
+        .arg("record")
+        .arg("--jobs")
+        .arg("4")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let captured: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.starts_with("Capturing: "))
+        .collect();
+    assert_eq!(
+        captured,
+        vec![
+            "Capturing: sleep 0.3; echo first",
+            "Capturing: echo second",
+            "Capturing: echo third",
+            "Capturing: echo fourth",
+        ]
+    );
+
+    let output = rere_cmd()
+        .arg(&config_path)
+        .arg("replay")
+        .arg("-j")
+        .arg("0")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("All tests passed!"));
+}
+
+#[test]
+fn test_timeouts() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let test_list = temp.path().join("rere/test.list");
+    fs::write(&test_list, "// @timeout: 0.2\nsleep 30\n").unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+    let snapshot_path = temp.path().join("rere/snapshots/test.list.bi");
+    let snapshot = fs::read_to_string(&snapshot_path).unwrap();
+    assert!(snapshot.contains(":i timed_out 1\n"));
+
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(output.status.success());
+
+    // The default timeout from the config applies to tests without their own.
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config.replace("jobs = 1", "jobs = 1\ntimeout = 0.2"),
+    )
+    .unwrap();
+    fs::write(&test_list, "sleep 0.5\n").unwrap();
+    rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    let snapshot = fs::read_to_string(&snapshot_path).unwrap();
+    assert!(snapshot.contains(":i timed_out 1\n"));
+
+    // A test that no longer times out is a distinct outcome, not a return code change.
+    fs::write(&test_list, "// @timeout: 5\nsleep 0.5\n").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains("Unexpected outcome:"));
+    assert!(stdout.contains("Expected: timed out -> Actual: exited with code 0"));
+}
+
+#[test]
+fn test_interpreter_and_wrapper() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let config = fs::read_to_string(&config_path).unwrap();
+    assert!(config.contains("interpreter = [\n    \"sh\",\n    \"-c\",\n]"));
+    let test_list = temp.path().join("rere/test.list");
+    fs::write(&test_list, "echo $GREETING\n").unwrap();
+
+    // The wrapper prefixes every command.
+    let wrapped = config.replace("wrapper = []", "wrapper = [\"env\", \"GREETING=hi\"]");
+    fs::write(&config_path, &wrapped).unwrap();
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+    let snapshot_path = temp.path().join("rere/snapshots/test.list.bi");
+    let snapshot = fs::read_to_string(&snapshot_path).unwrap();
+    assert!(snapshot.contains(":b interpreter 5\nsh -c\n"));
+    assert!(snapshot.contains(":b wrapper 17\nenv 'GREETING=hi'\n"));
+    assert!(snapshot.contains(":b stdout 3\nhi\n"));
+
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(output.status.success());
+
+    // Replaying under a different wrapper is flagged even though the output matches.
+    let config = fs::read_to_string(&config_path).unwrap();
+    let rewrapped = config.replace("\"GREETING=hi\",", "\"GREETING=hi\",\n    \"OTHER=1\",");
+    fs::write(&config_path, rewrapped).unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains("Unexpected wrapper:"));
+    assert!(stdout.contains("Expected: env GREETING=hi -> Actual: env GREETING=hi OTHER=1"));
+    assert!(!stdout.contains("Replaying:"));
+}
+
+#[test]
+fn test_environment() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config.replace("[env.set]\n", "[env.set]\nGREETING = \"hi\"\n"),
+    )
+    .unwrap();
+    let test_list = temp.path().join("rere/test.list");
+    fs::write(
+        &test_list,
+        "echo $GREETING\n// @env: GREETING=hey\necho $GREETING\n",
+    )
+    .unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+    let snapshot_path = temp.path().join("rere/snapshots/test.list.bi");
+    let snapshot = fs::read_to_string(&snapshot_path).unwrap();
+    assert!(snapshot.contains(":i env_digest "));
+    assert!(snapshot.contains(":b stdout 3\nhi\n"));
+    assert!(snapshot.contains(":b stdout 4\nhey\n"));
+
+    // Inherited variables aren't part of the digest.
+    let output = rere_cmd()
+        .env("RERE_UNRELATED", "1")
+        .arg(&config_path)
+        .arg("replay")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // A changed fixed value is reported before the output it causes.
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config.replace("GREETING = \"hi\"", "GREETING = \"hello\""),
+    )
+    .unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains("Unexpected environment digest:"));
+}
+
+#[test]
+fn test_workdir() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    fs::create_dir_all(temp.path().join("data/nested")).unwrap();
+    fs::write(temp.path().join("data/input.txt"), "root data\n").unwrap();
+    fs::write(temp.path().join("data/nested/input.txt"), "nested data\n").unwrap();
+    fs::write(
+        temp.path().join("rere/test.list"),
+        "cat data/input.txt\n// @cd: data/nested\ncat input.txt\n",
+    )
+    .unwrap();
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config.replace("run_from_root = false", "run_from_root = true"),
+    )
+    .unwrap();
+
+    // Relative paths resolve from the project root wherever rere is invoked from.
+    let output = rere_cmd()
+        .current_dir(temp.path())
+        .arg("rere/rere.toml")
+        .arg("record")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let snapshot_path = temp.path().join("rere/snapshots/test.list.bi");
+    let snapshot = fs::read_to_string(&snapshot_path).unwrap();
+    assert!(snapshot.contains(":b stdout 10\nroot data\n"));
+    assert!(snapshot.contains(":b stdout 12\nnested data\n"));
+
+    let output = rere_cmd()
+        .current_dir(temp.path().join("rere/snapshots"))
+        .arg("../rere.toml")
+        .arg("replay")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // An explicit workdir is relative to the config file directory.
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config.replace(
+            "run_from_root = true",
+            "run_from_root = false\nworkdir = \"..\"",
+        ),
+    )
+    .unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(output.status.success());
+
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config.replace("run_from_root = false", "run_from_root = true"),
+    )
+    .unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success());
+    assert!(stderr.contains("`workdir` and `run_from_root` can't both be set"));
+}
+
+#[test]
+fn test_stdin() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    fs::write(temp.path().join("rere/input.txt"), "b\na\n").unwrap();
+    let test_list = temp.path().join("rere/test.list");
+    fs::write(
+        &test_list,
+        "// @stdin: hello\ntr a-z A-Z\n// @stdin-file: input.txt\nsort\ncat\n",
+    )
+    .unwrap();
+
+    // `cat` reads /dev/null rather than waiting for input.
+    let output = rere_cmd()
+        .arg(&config_path)
+        .arg("record")
+        .timeout(std::time::Duration::from_secs(30))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let snapshot_path = temp.path().join("rere/snapshots/test.list.bi");
+    let snapshot = fs::read_to_string(&snapshot_path).unwrap();
+    assert!(snapshot.contains(":b stdin 6\nhello\n"));
+    assert!(snapshot.contains(":b stdout 6\nHELLO\n"));
+    assert!(snapshot.contains(":b stdin 4\nb\na\n"));
+    assert!(snapshot.contains(":b stdout 4\na\nb\n"));
+
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(output.status.success());
+
+    // Changed input is reported before the output it causes.
+    fs::write(temp.path().join("rere/input.txt"), "c\na\n").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains("Unexpected stdin:"));
+}
+
+#[test]
+fn test_selection() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+    let counter = temp.path().join("counter");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let test_list = temp.path().join("rere/test.list");
+    fs::write(
+        &test_list,
+        format!(
+            "echo one\necho two\ncat {}\necho three\n",
+            counter.display()
+        ),
+    )
+    .unwrap();
+    fs::write(&counter, "1\n").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+
+    // Only the selected commands are replayed.
+    fs::write(&counter, "2\n").unwrap();
+    let output = rere_cmd()
+        .arg(&config_path)
+        .arg("replay")
+        .args(["--filter", "^echo", "--exclude", "two"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert!(stdout.contains("Replaying: echo one"));
+    assert!(stdout.contains("Replaying: echo three"));
+    assert!(!stdout.contains("Replaying: echo two"));
+    assert!(!stdout.contains("Replaying: cat"));
+
+    let output = rere_cmd()
+        .arg(&config_path)
+        .arg("replay")
+        .args(["--index", "3"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    // Re-recording a single command keeps the other entries of the snapshot.
+    let output = rere_cmd()
+        .arg(&config_path)
+        .arg("record")
+        .args(["--index", "3"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert_eq!(stdout.matches("Capturing:").count(), 1);
+    let snapshot = fs::read_to_string(temp.path().join("rere/snapshots/test.list.bi")).unwrap();
+    assert!(snapshot.contains(":i count 4\n"));
+    assert!(snapshot.contains(":b stdout 4\none\n"));
+    assert!(snapshot.contains(":b stdout 2\n2\n"));
+
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(output.status.success());
+
+    let output = rere_cmd()
+        .arg(&config_path)
+        .arg("replay")
+        .args(["--index", "2-5"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success());
+    assert!(stderr.contains("Index 5 is out of range"));
+}
+
+#[test]
+fn test_annotations() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let test_list = temp.path().join("rere/test.list");
+    fs::write(
+        &test_list,
+        "// @name: greet\n// @tags: fast\necho hi\n\
+         // @name: usage\n// @tags: slow\n// @expect-exit: 2\nsh -c 'exit 2'\n",
+    )
+    .unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert!(stdout.contains("Capturing: greet"));
+    assert!(stdout.contains("Capturing: usage"));
+
+    let output = rere_cmd()
+        .arg(&config_path)
+        .arg("replay")
+        .args(["--tag", "slow"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert_eq!(stdout.matches("Replaying:").count(), 1);
+    assert!(stdout.contains("Replaying: usage"));
+
+    // A command exiting with another code than expected isn't recorded.
+    fs::write(
+        &test_list,
+        "// @name: greet\necho hi\n// @name: usage\n// @expect-exit: 2\ntrue\n",
+    )
+    .unwrap();
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains("Expected exit code 2, got 0"));
+    assert!(stderr.contains("1 command(s) exited with an unexpected code"));
+    let snapshot = fs::read_to_string(temp.path().join("rere/snapshots/test.list.bi")).unwrap();
+    assert!(snapshot.contains("sh -c 'exit 2'"));
+
+    // Replay checks the expected exit code too.
+    let code = temp.path().join("code");
+    fs::write(&code, "2").unwrap();
+    fs::write(
+        &test_list,
+        format!(
+            "// @expect-exit: 2\nsh -c \"exit $(cat '{}')\"\n",
+            code.display()
+        ),
+    )
+    .unwrap();
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+    fs::write(&code, "3").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains("Unexpected exit code:"));
+    assert!(stdout.contains("Expected: 2 -> Actual: 3"));
+    assert!(!stdout.contains("Unexpected return code:"));
+}
+
+#[test]
+fn test_multi_line_commands() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    fs::write(
+        temp.path().join("rere/test.list"),
+        "printf '%s\\n' one \\\n    two\n\
+         ```\nfor word in a b; do\n  echo \"$word  |\"\ndone\n```\n\
+         cat <<EOF\n  indented\nEOF\n",
+    )
+    .unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+    let snapshot = fs::read_to_string(temp.path().join("rere/snapshots/test.list.bi")).unwrap();
+    assert!(snapshot.contains(":i count 3\n"));
+    assert!(snapshot.contains(":b shell 27\nprintf '%s\\n' one \\\n    two\n"));
+    assert!(snapshot.contains(":b stdout 8\none\ntwo\n"));
+    assert!(snapshot.contains(":b stdout 10\na  |\nb  |\n"));
+    assert!(snapshot.contains(":b stdout 11\n  indented\n"));
+
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_toml_test_file() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd()
+        .arg(&config_path)
+        .args(["init", "--test-file", "tests.toml"])
+        .output()
+        .unwrap();
+    let test_file = temp.path().join("rere/tests.toml");
+    fs::write(
+        &test_file,
+        r#"
+[[test]]
+name = "sorted"
+command = "sort"
+stdin = "b\na\n"
+
+[[test]]
+name = "greeting"
+command = "echo $GREETING"
+env = { GREETING = "hi" }
+"#,
+    )
+    .unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert!(stdout.contains("Capturing: sorted"));
+    let snapshot = fs::read_to_string(temp.path().join("rere/snapshots/tests.toml.bi")).unwrap();
+    assert!(snapshot.contains(":b stdout 4\na\nb\n"));
+    assert!(snapshot.contains(":b stdout 3\nhi\n"));
+
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(output.status.success());
+
+    // Errors point at the offending test.
+    fs::write(&test_file, "[[test]]\ncommand = \"true\"\ntimeout = 0\n").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success());
+    assert!(stderr.contains("Test 1: invalid timeout"));
+}
+
+#[test]
+fn test_suites_and_includes() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let base_dir = temp.path().join("rere");
+    fs::write(base_dir.join("common.list"), "echo shared\n").unwrap();
+    fs::write(
+        base_dir.join("cli.list"),
+        "// @include common.list\necho cli\n",
+    )
+    .unwrap();
+    fs::write(base_dir.join("api.list"), "echo api\n").unwrap();
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config + "\n[suites]\ncli = \"cli.list\"\napi = \"api.list\"\n",
+    )
+    .unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert!(stdout.contains("Suite: api\nCapturing: echo api\nSuite: cli\n"));
+    let snapshot = fs::read_to_string(base_dir.join("snapshots/cli.bi")).unwrap();
+    assert!(snapshot.contains(":b stdout 7\nshared\n"));
+    assert!(snapshot.contains(":b stdout 4\ncli\n"));
+    assert!(base_dir.join("snapshots/api.bi").exists());
+
+    // Only the selected suite is replayed.
+    fs::write(base_dir.join("api.list"), "echo changed\n").unwrap();
+    let output = rere_cmd()
+        .arg(&config_path)
+        .args(["replay", "--suite", "cli"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert!(!stdout.contains("Suite: api"));
+
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(!output.status.success());
+    let config = fs::read_to_string(&config_path).unwrap();
+    assert!(config.contains("suite = \"api\""));
+
+    let output = rere_cmd()
+        .arg(&config_path)
+        .args(["replay", "--suite", "web"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Unknown suite `web`"));
+
+    // Including a file that includes it back is a cycle.
+    fs::write(base_dir.join("common.list"), "// @include cli.list\n").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success());
+    assert!(stderr.contains("Include cycle: "));
+    assert!(stderr.contains("cli.list -> "));
+}
+
+#[test]
+fn test_normalization() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let data = temp.path().join("data.txt");
+    fs::write(&data, "2024-01-01 ok\n").unwrap();
+    fs::write(
+        temp.path().join("rere/test.list"),
+        format!(
+            "// @normalize: pid \\d+ => pid <PID>\necho pid $$\ncat {}\n",
+            data.display()
+        ),
+    )
+    .unwrap();
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config.replace("builtin = []", "builtin = [\"iso-date\"]"),
+    )
+    .unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+    let snapshot = fs::read_to_string(temp.path().join("rere/snapshots/test.list.bi")).unwrap();
+    assert!(snapshot.contains(":b stdout 10\npid <PID>\n"));
+    assert!(snapshot.contains(":b stdout 10\n<DATE> ok\n"));
+
+    fs::write(&data, "2024-05-05 ok\n").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(output.status.success());
+
+    // Failures show the output before normalization.
+    fs::write(&data, "2024-05-05 failed\n").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains("  -<DATE> ok\n  +<DATE> failed\n"));
+    assert!(stdout.contains("  Actual before normalization:\n    2024-05-05 failed\n"));
+}
+
+#[test]
+fn test_comparison_modes() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let (lines, status, version) = (
+        temp.path().join("lines.txt"),
+        temp.path().join("status.txt"),
+        temp.path().join("version.txt"),
+    );
+    fs::write(&lines, "a\nb\n").unwrap();
+    fs::write(&status, "ready\n").unwrap();
+    fs::write(&version, "v1.0\n").unwrap();
+    fs::write(
+        temp.path().join("rere/test.list"),
+        format!(
+            "// @stdout: unordered\ncat {lines}\n\
+             // @stdout: contains ready\n// @stderr: ignore\n// @exit-code: ignore\n\
+             cat {status}; cat {status} >&2; exit $(wc -l < {status})\n\
+             // @stdout: regex ^v\\d+\\.\\d+\ncat {version}\n",
+            lines = lines.display(),
+            status = status.display(),
+            version = version.display()
+        ),
+    )
+    .unwrap();
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config.replace("fail_fast = true", "fail_fast = false"),
+    )
+    .unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+
+    fs::write(&lines, "b\na\n").unwrap();
+    fs::write(&status, "still ready\nok\n").unwrap();
+    fs::write(&version, "v2.3\n").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    assert!(output.status.success());
+
+    fs::write(&lines, "b\nc\n").unwrap();
+    fs::write(&version, "beta\n").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains(
+        "Unexpected stdout (unordered):\n  stdout does not have the snapshot's lines\n"
+    ));
+    assert!(stdout.contains("Unexpected stdout (regex):\n  stdout does not match the regex\n"));
+    assert!(!stdout.contains("Unexpected stderr"));
+    assert!(!stdout.contains("Unexpected return code"));
+
+    // Texts differing only in the final newline have no line diff to show.
+    fs::write(
+        temp.path().join("rere/tests.toml"),
+        "[[test]]\ncommand = \"printf a\"\nstdout = { contains = \"a\\n\" }\n",
+    )
+    .unwrap();
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, config.replace("test.list", "tests.toml")).unwrap();
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains(
+        "Unexpected stdout (contains):\n  stdout does not contain the expected text\n"
+    ));
+    assert!(!stdout.contains("valid UTF-8"));
+    assert!(!stdout.contains("--- expected"));
+}
+
+#[test]
+fn test_unified_diff() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let data = temp.path().join("data.txt");
+    fs::write(&data, "1\n2\n3\n4\n5\n6\n7\n8\n").unwrap();
+    fs::write(
+        temp.path().join("rere/test.list"),
+        format!("cat {}\n", data.display()),
+    )
+    .unwrap();
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, config.replace("context = 3", "context = 1")).unwrap();
+
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+
+    // An inserted line doesn't shift every following line into the diff.
+    fs::write(&data, "1\n2\ninserted\n3\n4\n5\n6\n7\n8\n").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains("Unexpected stdout:\n  --- expected\n  +++ actual\n"));
+    assert!(stdout.contains("  @@ -2,2 +2,3 @@\n   2\n  +inserted\n   3\n"));
+    assert!(!stdout.contains(" 4\n"));
+
+    let config = fs::read_to_string(&config_path).unwrap();
+    assert!(config.contains("lines = [\n"));
+    assert!(config.contains("\"+inserted\""));
+}
+
+#[test]
+fn test_colored_diff() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let data = temp.path().join("data.txt");
+    fs::write(&data, "size 10\n").unwrap();
+    fs::write(
+        temp.path().join("rere/test.list"),
+        format!("cat {}\n", data.display()),
+    )
+    .unwrap();
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+
+    fs::write(&data, "size 12").unwrap();
+    let replay = |color: Option<&str>| {
+        let mut cmd = rere_cmd();
+        cmd.arg(&config_path).arg("replay");
+        if let Some(color) = color {
+            cmd.arg(format!("--color={}", color));
+        }
+        let output = cmd.output().unwrap();
+        assert!(!output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // Not a terminal, so no colors by default.
+    let stdout = replay(None);
+    assert!(!stdout.contains('\x1b'));
+    assert!(stdout.contains("  -size 10\n  +size 12\n  +\\ No newline at end of file\n"));
+    assert_eq!(replay(Some("never")), stdout);
+
+    let stdout = replay(Some("always"));
+    assert!(stdout.contains("\x1b[31m-\x1b[0m\x1b[31msize \x1b[0m\x1b[7;31m10\x1b[0m\n"));
+    assert!(stdout.contains("\x1b[32m+\x1b[0m\x1b[32msize \x1b[0m\x1b[7;32m12\x1b[0m\n"));
+    assert!(stdout.contains("\x1b[2m\\ No newline at end of file\x1b[0m"));
+}
+
+#[test]
+fn test_binary_diff() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let data = temp.path().join("data.bin");
+    fs::write(&data, b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR").unwrap();
+    fs::write(
+        temp.path().join("rere/test.list"),
+        format!("cat {}\n", data.display()),
+    )
+    .unwrap();
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+
+    fs::write(&data, b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0eIHDR\xff").unwrap();
+    let output = rere_cmd().arg(&config_path).arg("replay").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains(
+        "Unexpected stdout:\n  Binary output differs from byte 11 (0xb), 16 -> 17 bytes (+1)\n"
+    ));
+    assert!(stdout.contains(
+        "  -00000000  89 50 4e 47 0d 0a 1a 0a 00 00 00 0d 49 48 44 52  |.PNG........IHDR|\n"
+    ));
+    assert!(stdout.contains(
+        "  +00000000  89 50 4e 47 0d 0a 1a 0a 00 00 00 0e 49 48 44 52  |.PNG........IHDR|\n"
+    ));
+    assert!(stdout.contains("  +00000010  ff"));
+    assert!(!stdout.contains('\u{fffd}'));
+
+    let config = fs::read_to_string(&config_path).unwrap();
+    assert!(config.contains("summary = \"Binary output differs from byte 11"));
+}
+
+#[test]
+fn test_junit_report() {
+    let temp = tempdir().unwrap();
+    let config_path = temp.path().join("rere/rere.toml");
+
+    rere_cmd().arg(&config_path).arg("init").output().unwrap();
+    let data = temp.path().join("data.txt");
+    fs::write(&data, "a\n").unwrap();
+    fs::write(
+        temp.path().join("rere/test.list"),
+        format!(
+            "// @name: greeting\necho '<hi>' >&2\ncat {}\necho later\n",
+            data.display()
+        ),
+    )
+    .unwrap();
+    let output = rere_cmd().arg(&config_path).arg("record").output().unwrap();
+    assert!(output.status.success());
+
+    fs::write(&data, "b\n").unwrap();
+    let report = temp.path().join("reports/junit.xml");
+    let output = rere_cmd()
+        .arg(&config_path)
+        .arg("replay")
+        .arg("--report")
+        .arg(format!("junit={}", report.display()))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let xml = fs::read_to_string(&report).unwrap();
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+    assert!(xml.contains("<testsuite name=\"rere\" tests=\"3\" failures=\"1\" skipped=\"1\" "));
+    assert!(xml.contains("<testcase name=\"greeting\" classname=\"rere\" time=\""));
+    assert!(xml.contains("<system-err>&lt;hi&gt;</system-err>"));
+    assert!(xml.contains(
+        "<failure message=\"Unexpected stdout\">--- expected\n+++ actual\n\
+         @@ -1,1 +1,1 @@\n-a\n+b</failure>\n      <system-out>b</system-out>"
+    ));
+    assert!(xml.contains(
+        "<testcase name=\"echo later\" classname=\"rere\" time=\"0.000\">\n      <skipped "
+    ));
+
+    let output = rere_cmd()
+        .arg(&config_path)
+        .arg("replay")
+        .arg("--report")
+        .arg("tap=report.tap")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("`tap` is not a report format, expected `junit`"));
+}